@@ -9,7 +9,7 @@ use bevy_inspector_egui::{
 };
 use bevy_tilt_five::{
     AvailableGlasses, BoardBundle, DebugGizmo, GlassesInfo, TiltFiveClientEvent, TiltFiveCommands,
-    TiltFivePlugin,
+    TiltFivePlugin, TiltFiveService,
 };
 
 fn main() {
@@ -21,7 +21,7 @@ fn main() {
         .insert_resource(ClearColor(Color::BLACK))
         .add_plugins(DefaultPlugins)
         .add_plugin(EguiPlugin)
-        .add_plugin(TiltFivePlugin)
+        .add_plugin(TiltFivePlugin::default())
         .add_startup_system(setup)
         .add_system(connect_glasses)
         .add_plugin(WorldInspectorPlugin)
@@ -72,6 +72,7 @@ fn connect_glasses(
     mut events: EventWriter<TiltFiveCommands>,
     mut read_events: EventReader<TiltFiveClientEvent>,
     glasses: Res<AvailableGlasses>,
+    service: Res<TiltFiveService>,
 ) {
     let connected_glasses = glasses
         .glasses
@@ -104,6 +105,15 @@ fn connect_glasses(
         .collect::<Vec<_>>();
     let ctx = egui_context.ctx_mut();
     egui::Window::new("T5 Status").show(ctx, |ui| {
+        if let Some(version) = &service.version {
+            ui.label(format!("Service Version: {version}"));
+        }
+        if service.attention_required {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                "Open the Tilt Five control panel - it needs your attention",
+            );
+        }
         ui.label("Available Glasses:");
         for (key, val) in glasses.glasses.iter() {
             let label = Into::<&str>::into(key);
@@ -134,7 +144,7 @@ fn connect_glasses(
         }
 
         for evt in read_events.iter() {
-            if let TiltFiveClientEvent::GlassesPoseChanged(_, t, _, o) = evt {
+            if let TiltFiveClientEvent::GlassesPoseChanged(_, t, _, o, _) = evt {
                 let t_p = t.translation;
                 let t_r = t.rotation;
                 let o_p = o.translation;
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy_tilt_five::{BoardBundle, DebugGizmo, TiltFiveSimulatorPlugin};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_plugins(DefaultPlugins)
+        .add_plugin(TiltFiveSimulatorPlugin::default())
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // board
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 0.7 })),
+        material: materials.add(Color::rgb(0.2, 0.2, 0.2).into()),
+        ..default()
+    });
+
+    // something to look at
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 0.1 })),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+        transform: Transform::from_xyz(0.0, 0.05, 0.0),
+        ..default()
+    });
+
+    // Gizmo
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_scale(0.05 * Vec3::ONE)),
+        DebugGizmo(Color::RED),
+    ));
+
+    // light
+    commands.spawn(PointLightBundle {
+        point_light: PointLight {
+            intensity: 1500.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    // camera
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(-1.0, 1.0, 1.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    commands.spawn(BoardBundle {
+        ..Default::default()
+    });
+}
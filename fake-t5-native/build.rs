@@ -0,0 +1,22 @@
+extern crate bindgen;
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=../t5-sdk/include/TiltFiveNative.h");
+
+    // Only the types and constants - the functions are what this crate provides.
+    let bindings = bindgen::Builder::default()
+        .header("../t5-sdk/include/TiltFiveNative.h")
+        .allowlist_type("T5_.*")
+        .allowlist_var("T5_.*")
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks))
+        .generate()
+        .expect("Unable to generate bindings");
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindings
+        .write_to_file(out_path.join("bindings.rs"))
+        .expect("Couldn't write bindings!");
+}
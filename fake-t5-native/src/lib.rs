@@ -0,0 +1,1046 @@
+//! A fake `TiltFiveNative` library.
+//!
+//! Exports the same `t5*` symbols as the real SDK library, backed by an
+//! in-process simulated service, so anything loading the library at runtime
+//! (like `bridge::ffi`) can be tested without glasses or the Tilt Five service.
+//!
+//! The simulated service is scripted through the extra `fakeT5*` exports below.
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(clippy::missing_safety_doc)]
+
+pub mod ffi {
+    #![allow(non_upper_case_globals)]
+    #![allow(non_camel_case_types)]
+    #![allow(non_snake_case)]
+    #![allow(dead_code)]
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::{c_char, c_void, CStr},
+    ptr,
+    sync::{Mutex, MutexGuard},
+};
+
+use ffi::*;
+
+struct Service {
+    glasses: HashMap<String, Glasses>,
+    service_version: String,
+    attention_required: i64,
+    changed_system_params: Vec<T5_ParamSys>,
+    /// Keyed by function and the glasses the call is for, so tests scripting
+    /// different glasses can't take each other's errors
+    injected_errors: HashMap<(String, Option<String>), VecDeque<T5_Result>>,
+}
+
+struct Glasses {
+    friendly_name: String,
+    display_name: Option<String>,
+    pose: Option<T5_GlassesPose>,
+    ipd: f64,
+    state: T5_ConnectionState,
+    graphics_api: T5_GraphicsApi,
+    wands: Vec<T5_WandHandle>,
+    wand_stream: bool,
+    wand_events: VecDeque<T5_WandStreamEvent>,
+    changed_params: Vec<T5_ParamGlasses>,
+    frames_sent: u32,
+    camera_streams: Vec<u8>,
+    empty_cam_buffers: VecDeque<CamBuffer>,
+    pending_cam_images: VecDeque<CamFrame>,
+}
+
+struct CamBuffer(T5_CamImage);
+
+// The pixel pointer belongs to the client, which promises to keep it alive
+// until it's returned by `t5GetFilledCamImageBuffer` or cancelled.
+unsafe impl Send for CamBuffer {}
+
+struct CamFrame {
+    image: T5_CamImage,
+    pixels: Vec<u8>,
+}
+
+unsafe impl Send for CamFrame {}
+
+struct Context {
+    _application_id: String,
+}
+
+struct GlassesHandle {
+    id: String,
+}
+
+static SERVICE: Mutex<Option<Service>> = Mutex::new(None);
+
+fn service() -> MutexGuard<'static, Option<Service>> {
+    let mut service = SERVICE.lock().unwrap_or_else(|e| e.into_inner());
+    if service.is_none() {
+        *service = Some(Service {
+            glasses: HashMap::new(),
+            service_version: "fake-1.0.0".to_string(),
+            attention_required: 0,
+            changed_system_params: vec![],
+            injected_errors: HashMap::new(),
+        });
+    }
+    service
+}
+
+/// Run `f` against the simulated service, unless an error was injected for
+/// `function` on these `glasses` - `None` for calls that aren't about any
+fn call(
+    function: &str,
+    glasses: Option<&str>,
+    f: impl FnOnce(&mut Service) -> T5_Result,
+) -> T5_Result {
+    let mut service = service();
+    let service = service.as_mut().unwrap();
+    let key = (function.to_string(), glasses.map(str::to_string));
+    if let Some(errors) = service.injected_errors.get_mut(&key) {
+        if let Some(err) = errors.pop_front() {
+            return err;
+        }
+    }
+    f(service)
+}
+
+/// Like `call`, but for functions taking a `T5_Glasses`
+unsafe fn call_glasses(
+    function: &str,
+    glasses: T5_Glasses,
+    f: impl FnOnce(&mut Glasses) -> T5_Result,
+) -> T5_Result {
+    let Some(handle) = (glasses as *const GlassesHandle).as_ref() else {
+        return T5_ERROR_NO_CONTEXT;
+    };
+    call(function, Some(&handle.id), |service| {
+        match service.glasses.get_mut(&handle.id) {
+            Some(glasses) => f(glasses),
+            None => T5_ERROR_DEVICE_LOST,
+        }
+    })
+}
+
+/// Like `call_glasses`, for functions that need an exclusive connection
+unsafe fn call_connected(
+    function: &str,
+    glasses: T5_Glasses,
+    f: impl FnOnce(&mut Glasses) -> T5_Result,
+) -> T5_Result {
+    call_glasses(function, glasses, |glasses| match glasses.state {
+        T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection => f(glasses),
+        T5_ConnectionState_kT5_ConnectionState_Disconnected => T5_ERROR_DEVICE_LOST,
+        _ => T5_ERROR_NOT_CONNECTED,
+    })
+}
+
+unsafe fn str_arg<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        None
+    } else {
+        CStr::from_ptr(value).to_str().ok()
+    }
+}
+
+/// Write `bytes` to a caller supplied buffer, following the SDK's
+/// "size in, size needed out" convention
+unsafe fn write_buffer(bytes: &[u8], buffer: *mut c_char, size: *mut usize) -> T5_Result {
+    if buffer.is_null() || size.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    let available = *size;
+    *size = bytes.len();
+    if bytes.len() > available {
+        return T5_ERROR_OVERFLOW;
+    }
+    ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buffer, bytes.len());
+    T5_SUCCESS
+}
+
+unsafe fn write_string(value: &str, buffer: *mut c_char, size: *mut usize) -> T5_Result {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    write_buffer(&bytes, buffer, size)
+}
+
+unsafe fn write_list<T: Copy>(values: &[T], buffer: *mut T, count: *mut u16) -> T5_Result {
+    if buffer.is_null() || count.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    let available = *count as usize;
+    *count = values.len() as u16;
+    if values.len() > available {
+        return T5_ERROR_OVERFLOW;
+    }
+    ptr::copy_nonoverlapping(values.as_ptr(), buffer, values.len());
+    T5_SUCCESS
+}
+
+fn gameboard_size(gameboard_type: T5_GameboardType) -> Option<T5_GameboardSize> {
+    let (x, y, z) = match gameboard_type {
+        T5_GameboardType_kT5_GameboardType_None => (0., 0., 0.),
+        T5_GameboardType_kT5_GameboardType_LE => (0.35, 0.35, 0.),
+        T5_GameboardType_kT5_GameboardType_XE => (0.7, 0.35, 0.),
+        T5_GameboardType_kT5_GameboardType_XE_Raised => (0.7, 0.35, 0.5),
+        _ => return None,
+    };
+    Some(T5_GameboardSize {
+        viewableExtentPositiveX: x,
+        viewableExtentNegativeX: x,
+        viewableExtentPositiveY: y,
+        viewableExtentNegativeY: y,
+        viewableExtentPositiveZ: z,
+    })
+}
+
+// -- C_Ctx --
+
+#[no_mangle]
+pub unsafe extern "C" fn t5CreateContext(
+    context: *mut T5_Context,
+    clientInfo: *const T5_ClientInfo,
+    _platformContext: *mut c_void,
+) -> T5_Result {
+    call("t5CreateContext", None, |_| {
+        let (Some(info), false) = (clientInfo.as_ref(), context.is_null()) else {
+            return T5_ERROR_INVALID_ARGS;
+        };
+        let Some(application_id) = str_arg(info.applicationId) else {
+            return T5_ERROR_INVALID_ARGS;
+        };
+        let ctx = Box::new(Context {
+            _application_id: application_id.to_string(),
+        });
+        *context = Box::into_raw(ctx) as T5_Context;
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5DestroyContext(context: *mut T5_Context) {
+    if let Some(ctx) = context.as_mut() {
+        if !ctx.is_null() {
+            drop(Box::from_raw(*ctx as *mut Context));
+            *ctx = ptr::null_mut();
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ListGlasses(
+    context: T5_Context,
+    buffer: *mut c_char,
+    bufferSize: *mut usize,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5ListGlasses", None, |service| {
+        let mut ids = service.glasses.keys().collect::<Vec<_>>();
+        ids.sort();
+        let mut bytes = vec![];
+        for id in ids {
+            bytes.extend_from_slice(id.as_bytes());
+            bytes.push(0);
+        }
+        bytes.push(0);
+        write_buffer(&bytes, buffer, bufferSize)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5CreateGlasses(
+    context: T5_Context,
+    id: *const c_char,
+    glasses: *mut T5_Glasses,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    let (Some(id), false) = (str_arg(id), glasses.is_null()) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    call("t5CreateGlasses", Some(id), |service| {
+        if !service.glasses.contains_key(id) {
+            return T5_ERROR_TARGET_NOT_FOUND;
+        }
+        let handle = Box::new(GlassesHandle { id: id.to_string() });
+        *glasses = Box::into_raw(handle) as T5_Glasses;
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5DestroyGlasses(glasses: *mut T5_Glasses) {
+    if let Some(handle) = glasses.as_mut() {
+        if !handle.is_null() {
+            drop(Box::from_raw(*handle as *mut GlassesHandle));
+            *handle = ptr::null_mut();
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetSystemIntegerParam(
+    context: T5_Context,
+    param: T5_ParamSys,
+    value: *mut i64,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5GetSystemIntegerParam", None, |service| match param {
+        T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired => {
+            *value = service.attention_required;
+            T5_SUCCESS
+        }
+        T5_ParamSys_kT5_ParamSys_UTF8_Service_Version => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetSystemFloatParam(
+    context: T5_Context,
+    param: T5_ParamSys,
+    _value: *mut f64,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5GetSystemFloatParam", None, |_| match param {
+        T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired
+        | T5_ParamSys_kT5_ParamSys_UTF8_Service_Version => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetSystemUtf8Param(
+    context: T5_Context,
+    param: T5_ParamSys,
+    buffer: *mut c_char,
+    bufferSize: *mut usize,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5GetSystemUtf8Param", None, |service| match param {
+        T5_ParamSys_kT5_ParamSys_UTF8_Service_Version => {
+            write_string(&service.service_version, buffer, bufferSize)
+        }
+        T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetChangedSystemParams(
+    context: T5_Context,
+    buffer: *mut T5_ParamSys,
+    count: *mut u16,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5GetChangedSystemParams", None, |service| {
+        let result = write_list(&service.changed_system_params, buffer, count);
+        if result == T5_SUCCESS {
+            service.changed_system_params.clear();
+        }
+        result
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGameboardSize(
+    context: T5_Context,
+    gameboardType: T5_GameboardType,
+    gameboardSize: *mut T5_GameboardSize,
+) -> T5_Result {
+    if context.is_null() {
+        return T5_ERROR_NO_CONTEXT;
+    }
+    call("t5GetGameboardSize", None, |_| {
+        match (gameboard_size(gameboardType), gameboardSize.as_mut()) {
+            (Some(size), Some(out)) => {
+                *out = size;
+                T5_SUCCESS
+            }
+            _ => T5_ERROR_INVALID_ARGS,
+        }
+    })
+}
+
+// -- C_Sys --
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ReserveGlasses(
+    glasses: T5_Glasses,
+    displayName: *const c_char,
+) -> T5_Result {
+    call_glasses("t5ReserveGlasses", glasses, |glasses| {
+        let Some(name) = str_arg(displayName) else {
+            return T5_ERROR_INVALID_ARGS;
+        };
+        match glasses.state {
+            T5_ConnectionState_kT5_ConnectionState_Disconnected => T5_ERROR_DEVICE_LOST,
+            T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection => {
+                T5_ERROR_ALREADY_CONNECTED
+            }
+            _ => {
+                glasses.display_name = Some(name.to_string());
+                glasses.state = T5_ConnectionState_kT5_ConnectionState_ExclusiveReservation;
+                T5_SUCCESS
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5SetGlassesDisplayName(
+    glasses: T5_Glasses,
+    displayName: *const c_char,
+) -> T5_Result {
+    call_glasses("t5SetGlassesDisplayName", glasses, |glasses| {
+        let Some(name) = str_arg(displayName) else {
+            return T5_ERROR_INVALID_ARGS;
+        };
+        match glasses.state {
+            T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection
+            | T5_ConnectionState_kT5_ConnectionState_ExclusiveReservation => {
+                glasses.display_name = Some(name.to_string());
+                T5_SUCCESS
+            }
+            _ => T5_ERROR_INVALID_STATE,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5EnsureGlassesReady(glasses: T5_Glasses) -> T5_Result {
+    call_glasses("t5EnsureGlassesReady", glasses, |glasses| {
+        match glasses.state {
+            T5_ConnectionState_kT5_ConnectionState_Disconnected => T5_ERROR_DEVICE_LOST,
+            T5_ConnectionState_kT5_ConnectionState_NotExclusivelyConnected => {
+                T5_ERROR_INVALID_STATE
+            }
+            _ => {
+                glasses.state = T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection;
+                T5_SUCCESS
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ReleaseGlasses(glasses: T5_Glasses) -> T5_Result {
+    call_glasses("t5ReleaseGlasses", glasses, |glasses| {
+        if glasses.state != T5_ConnectionState_kT5_ConnectionState_Disconnected {
+            glasses.state = T5_ConnectionState_kT5_ConnectionState_NotExclusivelyConnected;
+        }
+        glasses.display_name = None;
+        glasses.wand_stream = false;
+        glasses.camera_streams.clear();
+        glasses.empty_cam_buffers.clear();
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesConnectionState(
+    glasses: T5_Glasses,
+    connectionState: *mut T5_ConnectionState,
+) -> T5_Result {
+    if connectionState.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    let Some(handle) = (glasses as *const GlassesHandle).as_ref() else {
+        return T5_ERROR_NO_CONTEXT;
+    };
+    call("t5GetGlassesConnectionState", Some(&handle.id), |service| {
+        // Glasses that have been unplugged from the fake service report as disconnected
+        *connectionState = service
+            .glasses
+            .get(&handle.id)
+            .map(|glasses| glasses.state)
+            .unwrap_or(T5_ConnectionState_kT5_ConnectionState_Disconnected);
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesIdentifier(
+    glasses: T5_Glasses,
+    buffer: *mut c_char,
+    bufferSize: *mut usize,
+) -> T5_Result {
+    let Some(handle) = (glasses as *const GlassesHandle).as_ref() else {
+        return T5_ERROR_NO_CONTEXT;
+    };
+    call(
+        "t5GetGlassesIdentifier",
+        Some(&handle.id),
+        |_| match write_string(&handle.id, buffer, bufferSize) {
+            T5_ERROR_OVERFLOW => T5_ERROR_STRING_OVERFLOW,
+            result => result,
+        },
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesPose(
+    glasses: T5_Glasses,
+    _usage: T5_GlassesPoseUsage,
+    pose: *mut T5_GlassesPose,
+) -> T5_Result {
+    if pose.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_connected("t5GetGlassesPose", glasses, |glasses| match glasses.pose {
+        Some(value) => {
+            *pose = value;
+            T5_SUCCESS
+        }
+        None => T5_ERROR_TRY_AGAIN,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5InitGlassesGraphicsContext(
+    glasses: T5_Glasses,
+    graphicsApi: T5_GraphicsApi,
+    _graphicsContext: *mut c_void,
+) -> T5_Result {
+    call_glasses("t5InitGlassesGraphicsContext", glasses, |glasses| {
+        if graphicsApi == T5_GraphicsApi_kT5_GraphicsApi_None {
+            T5_ERROR_INVALID_ARGS
+        } else if glasses.graphics_api != T5_GraphicsApi_kT5_GraphicsApi_None {
+            T5_ERROR_INVALID_STATE
+        } else {
+            glasses.graphics_api = graphicsApi;
+            T5_SUCCESS
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ConfigureCameraStreamForGlasses(
+    glasses: T5_Glasses,
+    config: T5_CameraStreamConfig,
+) -> T5_Result {
+    call_connected("t5ConfigureCameraStreamForGlasses", glasses, |glasses| {
+        glasses
+            .camera_streams
+            .retain(|index| *index != config.cameraIndex);
+        if config.enabled {
+            glasses.camera_streams.push(config.cameraIndex);
+        }
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetFilledCamImageBuffer(
+    glasses: T5_Glasses,
+    image: *mut T5_CamImage,
+) -> T5_Result {
+    if image.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_connected("t5GetFilledCamImageBuffer", glasses, |glasses| {
+        let Some(frame) = glasses
+            .pending_cam_images
+            .iter()
+            .position(|frame| glasses.camera_streams.contains(&frame.image.cameraIndex))
+        else {
+            return T5_ERROR_TRY_AGAIN;
+        };
+        let Some(&CamBuffer(buffer)) = glasses.empty_cam_buffers.front() else {
+            return T5_ERROR_TRY_AGAIN;
+        };
+        // Both stay queued when the buffer is too small, so neither is lost
+        if (buffer.bufferSize as usize) < glasses.pending_cam_images[frame].pixels.len() {
+            return T5_ERROR_INVALID_BUFFER_SIZE;
+        }
+        glasses.empty_cam_buffers.pop_front();
+        let CamFrame {
+            image: mut filled,
+            pixels,
+        } = glasses.pending_cam_images.remove(frame).unwrap();
+        ptr::copy_nonoverlapping(pixels.as_ptr(), buffer.pixelData, pixels.len());
+        filled.pixelData = buffer.pixelData;
+        filled.bufferSize = buffer.bufferSize;
+        *image = filled;
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5SubmitEmptyCamImageBuffer(
+    glasses: T5_Glasses,
+    image: *mut T5_CamImage,
+) -> T5_Result {
+    let Some(image) = image.as_ref() else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    call_connected("t5SubmitEmptyCamImageBuffer", glasses, |glasses| {
+        let min_size = T5_MIN_CAM_IMAGE_BUFFER_WIDTH * T5_MIN_CAM_IMAGE_BUFFER_HEIGHT;
+        if image.pixelData.is_null() || image.bufferSize < min_size {
+            return T5_ERROR_INVALID_BUFFER_SIZE;
+        }
+        glasses.empty_cam_buffers.push_back(CamBuffer(*image));
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5CancelCamImageBuffer(glasses: T5_Glasses, buffer: *mut u8) -> T5_Result {
+    call_connected("t5CancelCamImageBuffer", glasses, |glasses| {
+        let before = glasses.empty_cam_buffers.len();
+        glasses
+            .empty_cam_buffers
+            .retain(|CamBuffer(image)| image.pixelData != buffer);
+        if glasses.empty_cam_buffers.len() == before {
+            T5_ERROR_INVALID_ARGS
+        } else {
+            T5_SUCCESS
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5SendFrameToGlasses(
+    glasses: T5_Glasses,
+    info: *const T5_FrameInfo,
+) -> T5_Result {
+    if info.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_connected("t5SendFrameToGlasses", glasses, |glasses| {
+        if glasses.graphics_api == T5_GraphicsApi_kT5_GraphicsApi_None {
+            return T5_ERROR_INVALID_STATE;
+        }
+        glasses.frames_sent += 1;
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesIntegerParam(
+    glasses: T5_Glasses,
+    _wand: T5_WandHandle,
+    param: T5_ParamGlasses,
+    _value: *mut i64,
+) -> T5_Result {
+    call_glasses("t5GetGlassesIntegerParam", glasses, |_| match param {
+        T5_ParamGlasses_kT5_ParamGlasses_Float_IPD
+        | T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesFloatParam(
+    glasses: T5_Glasses,
+    _wand: T5_WandHandle,
+    param: T5_ParamGlasses,
+    value: *mut f64,
+) -> T5_Result {
+    if value.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_glasses("t5GetGlassesFloatParam", glasses, |glasses| match param {
+        T5_ParamGlasses_kT5_ParamGlasses_Float_IPD => {
+            *value = glasses.ipd;
+            T5_SUCCESS
+        }
+        T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetGlassesUtf8Param(
+    glasses: T5_Glasses,
+    _wand: T5_WandHandle,
+    param: T5_ParamGlasses,
+    buffer: *mut c_char,
+    bufferSize: *mut usize,
+) -> T5_Result {
+    call_glasses("t5GetGlassesUtf8Param", glasses, |glasses| match param {
+        T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName => {
+            write_string(&glasses.friendly_name, buffer, bufferSize)
+        }
+        T5_ParamGlasses_kT5_ParamGlasses_Float_IPD => T5_ERROR_SETTING_WRONG_TYPE,
+        _ => T5_ERROR_INVALID_ARGS,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetChangedGlassesParams(
+    glasses: T5_Glasses,
+    buffer: *mut T5_ParamGlasses,
+    count: *mut u16,
+) -> T5_Result {
+    call_glasses("t5GetChangedGlassesParams", glasses, |glasses| {
+        let result = write_list(&glasses.changed_params, buffer, count);
+        if result == T5_SUCCESS {
+            glasses.changed_params.clear();
+        }
+        result
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ListWandsForGlasses(
+    glasses: T5_Glasses,
+    buffer: *mut T5_WandHandle,
+    count: *mut u8,
+) -> T5_Result {
+    if buffer.is_null() || count.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_glasses("t5ListWandsForGlasses", glasses, |glasses| {
+        let available = *count as usize;
+        *count = glasses.wands.len() as u8;
+        if glasses.wands.len() > available {
+            return T5_ERROR_OVERFLOW;
+        }
+        ptr::copy_nonoverlapping(glasses.wands.as_ptr(), buffer, glasses.wands.len());
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ConfigureWandStreamForGlasses(
+    glasses: T5_Glasses,
+    config: *const T5_WandStreamConfig,
+) -> T5_Result {
+    let Some(config) = config.as_ref() else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    call_glasses("t5ConfigureWandStreamForGlasses", glasses, |glasses| {
+        glasses.wand_stream = config.enabled;
+        if !config.enabled {
+            glasses.wand_events.clear();
+        }
+        T5_SUCCESS
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5ReadWandStreamForGlasses(
+    glasses: T5_Glasses,
+    event: *mut T5_WandStreamEvent,
+    _timeoutMs: u32,
+) -> T5_Result {
+    if event.is_null() {
+        return T5_ERROR_INVALID_ARGS;
+    }
+    call_glasses("t5ReadWandStreamForGlasses", glasses, |glasses| {
+        if !glasses.wand_stream {
+            return T5_ERROR_INVALID_STATE;
+        }
+        match glasses.wand_events.pop_front() {
+            Some(value) => {
+                *event = value;
+                T5_SUCCESS
+            }
+            None => T5_TIMEOUT,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn t5GetResultMessage(result: T5_Result) -> *const c_char {
+    let message: &'static [u8] = match result {
+        T5_SUCCESS => b"Success\0",
+        T5_TIMEOUT => b"Timeout\0",
+        T5_ERROR_NO_CONTEXT => b"No context\0",
+        T5_ERROR_NO_SERVICE => b"Service isn't connected\0",
+        T5_ERROR_INVALID_ARGS => b"Argument(s) are invalid\0",
+        T5_ERROR_DEVICE_LOST => b"Device lost\0",
+        T5_ERROR_TARGET_NOT_FOUND => b"Target (wand) not found\0",
+        T5_ERROR_INVALID_STATE => b"Incorrect state for the request\0",
+        T5_ERROR_OVERFLOW => b"Buffer overflow\0",
+        T5_ERROR_TRY_AGAIN => b"Target is not currently available\0",
+        T5_ERROR_UNAVAILABLE => b"Target is unavailable\0",
+        T5_ERROR_ALREADY_CONNECTED => b"The target is already connected\0",
+        T5_ERROR_NOT_CONNECTED => b"The target is not connected\0",
+        _ => b"Fake service error\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+// -- Scripting the fake service --
+
+/// Remove all glasses and injected errors, and restore the default system params
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5Reset() {
+    *SERVICE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Make the next `count` calls to `function` (e.g. `"t5EnsureGlassesReady"`)
+/// for the glasses `id` return `result`. A null `id` is for calls that aren't
+/// about any glasses, like `t5ListGlasses`.
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5InjectError(
+    function: *const c_char,
+    id: *const c_char,
+    result: T5_Result,
+    count: u32,
+) -> T5_Result {
+    let Some(function) = str_arg(function) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    let mut service = service();
+    let errors = service
+        .as_mut()
+        .unwrap()
+        .injected_errors
+        .entry((function.to_string(), str_arg(id).map(str::to_string)))
+        .or_default();
+    errors.extend((0..count).map(|_| result));
+    T5_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetServiceVersion(version: *const c_char) -> T5_Result {
+    let Some(version) = str_arg(version) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    let mut service = service();
+    let service = service.as_mut().unwrap();
+    service.service_version = version.to_string();
+    service
+        .changed_system_params
+        .push(T5_ParamSys_kT5_ParamSys_UTF8_Service_Version);
+    T5_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetAttentionRequired(required: i64) -> T5_Result {
+    let mut service = service();
+    let service = service.as_mut().unwrap();
+    service.attention_required = required;
+    service
+        .changed_system_params
+        .push(T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired);
+    T5_SUCCESS
+}
+
+/// Plug a new pair of glasses into the fake service
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5AddGlasses(
+    id: *const c_char,
+    friendlyName: *const c_char,
+) -> T5_Result {
+    let Some(id) = str_arg(id) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    let mut service = service();
+    service.as_mut().unwrap().glasses.insert(
+        id.to_string(),
+        Glasses {
+            friendly_name: str_arg(friendlyName).unwrap_or_default().to_string(),
+            display_name: None,
+            pose: None,
+            ipd: 59.,
+            state: T5_ConnectionState_kT5_ConnectionState_NotExclusivelyConnected,
+            graphics_api: T5_GraphicsApi_kT5_GraphicsApi_None,
+            wands: vec![],
+            wand_stream: false,
+            wand_events: VecDeque::new(),
+            changed_params: vec![],
+            frames_sent: 0,
+            camera_streams: vec![],
+            empty_cam_buffers: VecDeque::new(),
+            pending_cam_images: VecDeque::new(),
+        },
+    );
+    T5_SUCCESS
+}
+
+/// Remove the glasses from the service entirely - existing handles start reporting device lost
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5RemoveGlasses(id: *const c_char) -> T5_Result {
+    let Some(id) = str_arg(id) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    match service().as_mut().unwrap().glasses.remove(id) {
+        Some(_) => T5_SUCCESS,
+        None => T5_ERROR_TARGET_NOT_FOUND,
+    }
+}
+
+unsafe fn script_glasses(id: *const c_char, f: impl FnOnce(&mut Glasses)) -> T5_Result {
+    let Some(id) = str_arg(id) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    match service().as_mut().unwrap().glasses.get_mut(id) {
+        Some(glasses) => {
+            f(glasses);
+            T5_SUCCESS
+        }
+        None => T5_ERROR_TARGET_NOT_FOUND,
+    }
+}
+
+/// Set the pose the glasses report - a null pose makes `t5GetGlassesPose` return `T5_ERROR_TRY_AGAIN`
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetGlassesPose(
+    id: *const c_char,
+    pose: *const T5_GlassesPose,
+) -> T5_Result {
+    script_glasses(id, |glasses| glasses.pose = pose.as_ref().copied())
+}
+
+/// Set the IPD, in millimeters
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetGlassesIpd(id: *const c_char, ipd: f64) -> T5_Result {
+    script_glasses(id, |glasses| {
+        glasses.ipd = ipd;
+        glasses
+            .changed_params
+            .push(T5_ParamGlasses_kT5_ParamGlasses_Float_IPD);
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetGlassesFriendlyName(
+    id: *const c_char,
+    friendlyName: *const c_char,
+) -> T5_Result {
+    let Some(name) = str_arg(friendlyName) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    script_glasses(id, |glasses| {
+        glasses.friendly_name = name.to_string();
+        glasses
+            .changed_params
+            .push(T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName);
+    })
+}
+
+/// Force the connection state, e.g. `kT5_ConnectionState_Disconnected` to simulate a pulled cable
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5SetGlassesConnectionState(
+    id: *const c_char,
+    state: T5_ConnectionState,
+) -> T5_Result {
+    script_glasses(id, |glasses| glasses.state = state)
+}
+
+/// Read back the display name the client reserved the glasses with
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5GetGlassesDisplayName(
+    id: *const c_char,
+    buffer: *mut c_char,
+    bufferSize: *mut usize,
+) -> T5_Result {
+    let mut result = T5_SUCCESS;
+    let found = script_glasses(id, |glasses| {
+        result = write_string(
+            glasses.display_name.as_deref().unwrap_or_default(),
+            buffer,
+            bufferSize,
+        );
+    });
+    if found == T5_SUCCESS {
+        result
+    } else {
+        found
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5GetFramesSent(id: *const c_char) -> u32 {
+    let mut frames = 0;
+    script_glasses(id, |glasses| frames = glasses.frames_sent);
+    frames
+}
+
+/// Pair a wand with the glasses, queueing a connect event if the wand stream is enabled
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5ConnectWand(id: *const c_char, wand: T5_WandHandle) -> T5_Result {
+    script_glasses(id, |glasses| {
+        if !glasses.wands.contains(&wand) {
+            glasses.wands.push(wand);
+            push_wand_event(
+                glasses,
+                wand,
+                T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+            );
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5DisconnectWand(id: *const c_char, wand: T5_WandHandle) -> T5_Result {
+    script_glasses(id, |glasses| {
+        if glasses.wands.contains(&wand) {
+            glasses.wands.retain(|w| *w != wand);
+            push_wand_event(
+                glasses,
+                wand,
+                T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect,
+            );
+        }
+    })
+}
+
+/// Queue a raw wand stream event, e.g. a report
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5PushWandEvent(
+    id: *const c_char,
+    event: *const T5_WandStreamEvent,
+) -> T5_Result {
+    let Some(event) = event.as_ref() else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    script_glasses(id, |glasses| {
+        if glasses.wand_stream {
+            glasses.wand_events.push_back(*event);
+        }
+    })
+}
+
+fn push_wand_event(glasses: &mut Glasses, wand: T5_WandHandle, event_type: T5_WandStreamEventType) {
+    if glasses.wand_stream {
+        // Zeroed is a valid (all invalid flags) report
+        let report: T5_WandReport = unsafe { std::mem::zeroed() };
+        glasses.wand_events.push_back(T5_WandStreamEvent {
+            wandId: wand,
+            type_: event_type,
+            timestampNanos: 0,
+            report,
+        });
+    }
+}
+
+/// Queue a camera frame. It's delivered through `t5GetFilledCamImageBuffer` once
+/// the stream for its camera index is enabled and an empty buffer has been submitted.
+/// The pixel data is copied, and `image.pixelData`/`image.bufferSize` are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fakeT5PushCamImage(
+    id: *const c_char,
+    image: *const T5_CamImage,
+    pixels: *const u8,
+    length: usize,
+) -> T5_Result {
+    let (Some(image), false) = (image.as_ref(), pixels.is_null()) else {
+        return T5_ERROR_INVALID_ARGS;
+    };
+    let pixels = std::slice::from_raw_parts(pixels, length).to_vec();
+    script_glasses(id, |glasses| {
+        glasses.pending_cam_images.push_back(CamFrame {
+            image: *image,
+            pixels,
+        })
+    })
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    prelude::{Quat, Vec3},
+    utils::HashMap,
+};
+
+use crate::bridge::{
+    ffi::{
+        T5_FrameInfo, T5_GameboardType_kT5_GameboardType_LE, T5_GlassesPose, T5_WandHandle,
+        T5_WandReport, T5_WandStreamEvent, T5_WandStreamEventType,
+        T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+        T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect,
+        T5_WandStreamEventType_kT5_WandStreamEventType_Report,
+    },
+    CameraImage, ConnectionProgress, Glasses, GlassesCamera, GlassesConnectionState, GlassesParam,
+    SystemParam, T5Error, T5GameboardType, T5Result,
+};
+
+use super::TiltFiveBackend;
+
+/// An in-memory stand-in for the Tilt Five service.
+///
+/// Clones share the same state, so a test can keep one handle to script the
+/// glasses while the plugin owns another.
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    glasses: HashMap<Glasses, FakeGlasses>,
+    service_version: Option<String>,
+    attention_required: bool,
+    changed_system_params: Vec<SystemParam>,
+}
+
+/// The scripted state of a single pair of fake glasses
+#[derive(Clone)]
+pub struct FakeGlasses {
+    pub friendly_name: Option<String>,
+    /// The last display name the plugin set
+    pub display_name: Option<String>,
+    pub pose: T5_GlassesPose,
+    /// The smoothed pose for spectator views - the glasses pose when unset
+    pub spectator_pose: Option<T5_GlassesPose>,
+    /// In millimeters, like the SDK reports it
+    pub ipd: f32,
+    /// Params changed since the plugin last asked
+    pub changed_params: Vec<GlassesParam>,
+    /// The wands connected to the glasses
+    pub wands: Vec<T5_WandHandle>,
+    pub wand_events: VecDeque<T5_WandStreamEvent>,
+    /// The cameras the plugin is streaming from
+    pub camera_streams: Vec<GlassesCamera>,
+    /// Images waiting for the plugin - only ones from streaming cameras are handed over
+    pub camera_images: VecDeque<CameraImage>,
+    /// When set, every call for these glasses fails with this error
+    pub error: Option<T5Error>,
+    pub connected: bool,
+    /// What the service reports - setting `Disconnected` simulates pulling the cable
+    pub connection_state: GlassesConnectionState,
+    /// How many more times the glasses say they aren't ready yet while connecting
+    pub ready_after: u32,
+    pub frames_sent: usize,
+}
+
+impl Default for FakeGlasses {
+    fn default() -> Self {
+        Self {
+            friendly_name: None,
+            display_name: None,
+            pose: T5_GlassesPose {
+                timestampNanos: 0,
+                posGLS_GBD: Vec3::ZERO.into(),
+                rotToGLS_GBD: Quat::IDENTITY.into(),
+                gameboardType: T5_GameboardType_kT5_GameboardType_LE,
+            },
+            spectator_pose: None,
+            ipd: 59.,
+            changed_params: vec![],
+            wands: vec![],
+            wand_events: Default::default(),
+            camera_streams: vec![],
+            camera_images: Default::default(),
+            error: None,
+            connected: false,
+            connection_state: GlassesConnectionState::NotExclusivelyConnected,
+            ready_after: 0,
+            frames_sent: 0,
+        }
+    }
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_glasses<T: Into<Glasses>>(&self, glasses: T, friendly_name: Option<&str>) -> &Self {
+        self.state.lock().unwrap().glasses.insert(
+            glasses.into(),
+            FakeGlasses {
+                friendly_name: friendly_name.map(|name| name.to_string()),
+                ..Default::default()
+            },
+        );
+        self
+    }
+
+    pub fn remove_glasses(&self, glasses: &Glasses) -> &Self {
+        self.state.lock().unwrap().glasses.remove(glasses);
+        self
+    }
+
+    /// Run `f` against the scripted state of the glasses, if they exist
+    pub fn with_glasses<R>(
+        &self,
+        glasses: &Glasses,
+        f: impl FnOnce(&mut FakeGlasses) -> R,
+    ) -> Option<R> {
+        self.state.lock().unwrap().glasses.get_mut(glasses).map(f)
+    }
+
+    /// Set the pose in gameboard space, as the SDK would report it
+    pub fn set_pose(&self, glasses: &Glasses, position: Vec3, rotation: Quat) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.pose.timestampNanos += 1;
+            g.pose.posGLS_GBD = position.into();
+            g.pose.rotToGLS_GBD = rotation.into();
+        });
+        self
+    }
+
+    /// Set the spectator pose in gameboard space, separately from the glasses pose
+    pub fn set_spectator_pose(&self, glasses: &Glasses, position: Vec3, rotation: Quat) -> &Self {
+        self.with_glasses(glasses, |g| {
+            let mut pose = g.spectator_pose.unwrap_or(g.pose);
+            pose.timestampNanos += 1;
+            pose.posGLS_GBD = position.into();
+            pose.rotToGLS_GBD = rotation.into();
+            g.spectator_pose = Some(pose);
+        });
+        self
+    }
+
+    /// Put the glasses in front of a different kind of board
+    pub fn set_gameboard_type(&self, glasses: &Glasses, gameboard_type: T5GameboardType) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.pose.timestampNanos += 1;
+            g.pose.gameboardType = gameboard_type.into();
+        });
+        self
+    }
+
+    pub fn set_ipd(&self, glasses: &Glasses, ipd: f32) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.ipd = ipd;
+            g.changed_params.push(GlassesParam::Ipd);
+        });
+        self
+    }
+
+    /// Rename the glasses, like the user would in the control panel
+    pub fn set_friendly_name(&self, glasses: &Glasses, friendly_name: Option<&str>) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.friendly_name = friendly_name.map(|name| name.to_string());
+            g.changed_params.push(GlassesParam::FriendlyName);
+        });
+        self
+    }
+
+    pub fn push_wand_event(&self, glasses: &Glasses, event: T5_WandStreamEvent) -> &Self {
+        self.with_glasses(glasses, |g| g.wand_events.push_back(event));
+        self
+    }
+
+    /// Pair a wand with the glasses, reporting it on the wand stream
+    pub fn connect_wand(&self, glasses: &Glasses, wand: T5_WandHandle) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.wands.push(wand);
+            g.wand_events.push_back(wand_event(
+                wand,
+                T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+            ));
+        });
+        self
+    }
+
+    pub fn disconnect_wand(&self, glasses: &Glasses, wand: T5_WandHandle) -> &Self {
+        self.with_glasses(glasses, |g| {
+            g.wands.retain(|w| *w != wand);
+            g.wand_events.push_back(wand_event(
+                wand,
+                T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect,
+            ));
+        });
+        self
+    }
+
+    /// Send a report from a connected wand down the wand stream
+    pub fn push_wand_report(
+        &self,
+        glasses: &Glasses,
+        wand: T5_WandHandle,
+        report: T5_WandReport,
+    ) -> &Self {
+        let mut event = wand_event(wand, T5_WandStreamEventType_kT5_WandStreamEventType_Report);
+        event.timestampNanos = report.timestampNanos;
+        event.report = report;
+        self.push_wand_event(glasses, event)
+    }
+
+    pub fn push_camera_image(&self, glasses: &Glasses, image: CameraImage) -> &Self {
+        self.with_glasses(glasses, |g| g.camera_images.push_back(image));
+        self
+    }
+
+    pub fn set_connection_state(&self, glasses: &Glasses, state: GlassesConnectionState) -> &Self {
+        self.with_glasses(glasses, |g| g.connection_state = state);
+        self
+    }
+
+    pub fn set_error(&self, glasses: &Glasses, error: Option<T5Error>) -> &Self {
+        self.with_glasses(glasses, |g| g.error = error);
+        self
+    }
+
+    pub fn set_service_version(&self, version: &str) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state.service_version = Some(version.to_string());
+        state
+            .changed_system_params
+            .push(SystemParam::ServiceVersion);
+        self
+    }
+
+    pub fn set_attention_required(&self, required: bool) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state.attention_required = required;
+        state
+            .changed_system_params
+            .push(SystemParam::AttentionRequired);
+        self
+    }
+
+    pub fn is_connected(&self, glasses: &Glasses) -> bool {
+        self.with_glasses(glasses, |g| g.connected)
+            .unwrap_or_default()
+    }
+
+    pub fn frames_sent(&self, glasses: &Glasses) -> usize {
+        self.with_glasses(glasses, |g| g.frames_sent)
+            .unwrap_or_default()
+    }
+
+    fn connected<R>(
+        &self,
+        glasses: &Glasses,
+        f: impl FnOnce(&mut FakeGlasses) -> T5Result<R>,
+    ) -> T5Result<R> {
+        match self.state.lock().unwrap().glasses.get_mut(glasses) {
+            Some(FakeGlasses {
+                error: Some(err), ..
+            }) => Err(err.clone()),
+            Some(g) if g.connection_state == GlassesConnectionState::Disconnected => {
+                Err(T5Error::DeviceLost)
+            }
+            Some(g) if g.connected => f(g),
+            Some(_) => Err(T5Error::NotConnected),
+            None => Err(T5Error::GlassesNotFound(glasses.to_string())),
+        }
+    }
+}
+
+/// A wand stream event with an empty report
+fn wand_event(wand: T5_WandHandle, type_: T5_WandStreamEventType) -> T5_WandStreamEvent {
+    T5_WandStreamEvent {
+        wandId: wand,
+        type_,
+        timestampNanos: 0,
+        report: T5_WandReport::default(),
+    }
+}
+
+impl TiltFiveBackend for FakeBackend {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
+        Ok(self.state.lock().unwrap().glasses.keys().cloned().collect())
+    }
+
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress> {
+        match self.state.lock().unwrap().glasses.get_mut(glasses) {
+            Some(FakeGlasses {
+                error: Some(err), ..
+            }) => Err(err.clone()),
+            Some(g) if g.connected => Ok(ConnectionProgress::Ready),
+            Some(g) => match g.connection_state {
+                GlassesConnectionState::Disconnected => Err(T5Error::DeviceLost),
+                GlassesConnectionState::ExclusiveReservation if g.ready_after > 0 => {
+                    g.ready_after -= 1;
+                    Ok(ConnectionProgress::WaitingForReady)
+                }
+                GlassesConnectionState::ExclusiveReservation => {
+                    g.connected = true;
+                    g.connection_state = GlassesConnectionState::ExclusiveConnection;
+                    Ok(ConnectionProgress::Ready)
+                }
+                _ => {
+                    g.connection_state = GlassesConnectionState::ExclusiveReservation;
+                    Ok(ConnectionProgress::WaitingForReady)
+                }
+            },
+            None => Err(T5Error::GlassesNotFound(glasses.to_string())),
+        }
+    }
+
+    fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()> {
+        self.with_glasses(&glasses, |g| {
+            g.connected = false;
+            g.camera_streams.clear();
+            if g.connection_state != GlassesConnectionState::Disconnected {
+                g.connection_state = GlassesConnectionState::NotExclusivelyConnected;
+            }
+        });
+        Ok(())
+    }
+
+    fn set_glasses_display_name(&mut self, glasses: &Glasses, display_name: &str) -> T5Result<()> {
+        self.with_glasses(glasses, |g| g.display_name = Some(display_name.to_string()))
+            .ok_or_else(|| T5Error::GlassesNotFound(glasses.to_string()))
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.connected(glasses, |g| Ok(g.pose))
+    }
+
+    fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.connected(glasses, |g| Ok(g.spectator_pose.unwrap_or(g.pose)))
+    }
+
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState> {
+        match self.state.lock().unwrap().glasses.get(glasses) {
+            Some(FakeGlasses {
+                error: Some(err), ..
+            }) => Err(err.clone()),
+            Some(g) => Ok(g.connection_state),
+            // Like the service, glasses that have gone away entirely are disconnected
+            None => Ok(GlassesConnectionState::Disconnected),
+        }
+    }
+
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32> {
+        self.connected(glasses, |g| Ok(g.ipd))
+    }
+
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String> {
+        self.connected(glasses, |g| Ok(g.friendly_name.clone().unwrap_or_default()))
+    }
+
+    fn get_changed_glasses_params(&mut self, glasses: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        self.connected(glasses, |g| Ok(std::mem::take(&mut g.changed_params)))
+    }
+
+    fn list_wands(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        self.connected(glasses, |g| Ok(g.wands.clone()))
+    }
+
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>> {
+        self.connected(glasses, |g| Ok(g.wand_events.drain(..).collect()))
+    }
+
+    fn configure_camera_stream(
+        &mut self,
+        glasses: &Glasses,
+        camera: GlassesCamera,
+        enabled: bool,
+    ) -> T5Result<()> {
+        self.connected(glasses, |g| {
+            g.camera_streams.retain(|c| *c != camera);
+            if enabled {
+                g.camera_streams.push(camera);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_camera_images(&mut self, glasses: &Glasses) -> T5Result<Vec<CameraImage>> {
+        self.connected(glasses, |g| {
+            let streams = g.camera_streams.clone();
+            Ok(g.camera_images
+                .drain(..)
+                .filter(|image| streams.contains(&image.camera))
+                .collect())
+        })
+    }
+
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        _info: *const T5_FrameInfo,
+    ) -> T5Result<()> {
+        self.connected(glasses, |g| {
+            g.frames_sent += 1;
+            Ok(())
+        })
+    }
+
+    fn get_service_version(&mut self) -> T5Result<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .service_version
+            .clone()
+            .ok_or(T5Error::NoService)
+    }
+
+    fn is_attention_required(&mut self) -> T5Result<bool> {
+        Ok(self.state.lock().unwrap().attention_required)
+    }
+
+    fn get_changed_system_params(&mut self) -> T5Result<Vec<SystemParam>> {
+        Ok(std::mem::take(
+            &mut self.state.lock().unwrap().changed_system_params,
+        ))
+    }
+}
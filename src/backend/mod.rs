@@ -0,0 +1,364 @@
+#[cfg(any(test, feature = "fake"))]
+mod fake;
+mod recording;
+
+use std::ffi::c_void;
+
+use crate::bridge::{
+    ffi::{T5_FrameInfo, T5_GameboardSize, T5_GlassesPose, T5_WandHandle, T5_WandStreamEvent},
+    CameraImage, ConnectionProgress, Glasses, GlassesCamera, GlassesConnectionState, GlassesParam,
+    SystemParam, T5Client, T5Error, T5GameboardType, T5Result,
+};
+
+#[cfg(any(test, feature = "fake"))]
+pub use fake::{FakeBackend, FakeGlasses};
+pub use recording::{
+    RecordedFrame, RecordedSample, Recording, RecordingBackend, ReplayBackend, RECORDING_VERSION,
+};
+
+/// The operations the plugin needs from the Tilt Five service.
+///
+/// `T5Client` implements this against the real native library, while the
+/// simulator, replay and fake backends answer the same calls themselves - which
+/// lets the whole ECS pipeline run without glasses or the Tilt Five service
+/// installed.
+pub trait TiltFiveBackend {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>>;
+
+    /// Take the next step towards connecting to the glasses, without blocking.
+    ///
+    /// The plugin calls this once a frame until it reports `Ready` - an error
+    /// abandons the connection.
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress>;
+
+    fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()>;
+
+    /// Set the name the glasses show while they're reserved - kept for when
+    /// they next connect if they aren't connected yet
+    fn set_glasses_display_name(
+        &mut self,
+        _glasses: &Glasses,
+        _display_name: &str,
+    ) -> T5Result<()> {
+        Ok(())
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose>;
+
+    /// The smoother pose for rendering a spectator view on another display -
+    /// backends without one hand back the glasses pose
+    fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.get_glasses_pose(glasses)
+    }
+
+    /// How the service currently sees the glasses - polled every frame to
+    /// notice when they're unplugged
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState>;
+
+    /// The glasses IPD, in millimeters
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32>;
+
+    /// The glasses' friendly name - empty if they don't have one
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String>;
+
+    /// Drain the params that changed for the glasses since the last call - the
+    /// plugin only reads the IPD and name again when they're reported here
+    fn get_changed_glasses_params(&mut self, _glasses: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        Ok(vec![])
+    }
+
+    /// The wands connected to the glasses - read when they connect, with the
+    /// wand stream reporting changes after that
+    fn list_wands(&mut self, _glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        Ok(vec![])
+    }
+
+    /// Drain any pending wand stream events for the glasses
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>>;
+
+    /// Start or stop streaming images from one of the glasses' cameras
+    fn configure_camera_stream(
+        &mut self,
+        _glasses: &Glasses,
+        _camera: GlassesCamera,
+        _enabled: bool,
+    ) -> T5Result<()> {
+        Err(T5Error::Unsupported)
+    }
+
+    /// Drain the camera images filled since the last call
+    fn get_camera_images(&mut self, _glasses: &Glasses) -> T5Result<Vec<CameraImage>> {
+        Ok(vec![])
+    }
+
+    /// # Safety
+    /// `info` must point to a valid `T5_FrameInfo`, with texture handles matching
+    /// the graphics context the backend was set up with.
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        info: *const T5_FrameInfo,
+    ) -> T5Result<()>;
+
+    /// # Safety
+    /// `device` must be a live `ID3D11Device`, kept alive for as long as
+    /// frames are sent from it.
+    unsafe fn set_dx11_graphics_context(&mut self, _device: *mut c_void) -> T5Result<()> {
+        Ok(())
+    }
+
+    /// The viewable extents of a kind of board, in meters from its center -
+    /// backends without a service use `nominal_gameboard_size`
+    fn get_gameboard_size(
+        &mut self,
+        gameboard_type: T5GameboardType,
+    ) -> T5Result<T5_GameboardSize> {
+        Ok(nominal_gameboard_size(gameboard_type))
+    }
+
+    /// The version of the Tilt Five service - backends without a service
+    /// don't have one
+    fn get_service_version(&mut self) -> T5Result<String> {
+        Err(T5Error::Unsupported)
+    }
+
+    /// Whether the Tilt Five control panel needs the user's attention
+    fn is_attention_required(&mut self) -> T5Result<bool> {
+        Ok(false)
+    }
+
+    /// Drain the system params that changed since the last call
+    fn get_changed_system_params(&mut self) -> T5Result<Vec<SystemParam>> {
+        Ok(vec![])
+    }
+}
+
+impl TiltFiveBackend for T5Client {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
+        T5Client::list_glasses(self)
+    }
+
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress> {
+        T5Client::connect_glasses(self, glasses)
+    }
+
+    fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()> {
+        T5Client::release_glasses(self, glasses)
+    }
+
+    fn set_glasses_display_name(&mut self, glasses: &Glasses, display_name: &str) -> T5Result<()> {
+        T5Client::set_glasses_display_name(self, glasses, display_name)
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        T5Client::get_glasses_pose(self, glasses)
+    }
+
+    fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        T5Client::get_spectator_pose(self, glasses)
+    }
+
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState> {
+        T5Client::get_connection_state(self, glasses)
+    }
+
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32> {
+        T5Client::get_ipd(self, glasses)
+    }
+
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String> {
+        T5Client::get_glasses_name(self, glasses)
+    }
+
+    fn get_changed_glasses_params(&mut self, glasses: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        T5Client::get_changed_glasses_params(self, glasses)
+    }
+
+    fn list_wands(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        T5Client::list_wands(self, glasses)
+    }
+
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>> {
+        T5Client::get_wand_stream_events(self, glasses)
+    }
+
+    fn configure_camera_stream(
+        &mut self,
+        glasses: &Glasses,
+        camera: GlassesCamera,
+        enabled: bool,
+    ) -> T5Result<()> {
+        T5Client::configure_camera_stream(self, glasses, camera, enabled)
+    }
+
+    fn get_camera_images(&mut self, glasses: &Glasses) -> T5Result<Vec<CameraImage>> {
+        T5Client::get_camera_images(self, glasses)
+    }
+
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        info: *const T5_FrameInfo,
+    ) -> T5Result<()> {
+        T5Client::send_frame_to_glasses(self, glasses, info)
+    }
+
+    unsafe fn set_dx11_graphics_context(&mut self, device: *mut c_void) -> T5Result<()> {
+        T5Client::set_dx11_graphics_context(self, device)
+    }
+
+    fn get_gameboard_size(
+        &mut self,
+        gameboard_type: T5GameboardType,
+    ) -> T5Result<T5_GameboardSize> {
+        T5Client::get_gameboard_size(self, gameboard_type)
+    }
+
+    fn get_service_version(&mut self) -> T5Result<String> {
+        T5Client::get_service_version(self)
+    }
+
+    fn is_attention_required(&mut self) -> T5Result<bool> {
+        T5Client::is_attention_required(self)
+    }
+
+    fn get_changed_system_params(&mut self) -> T5Result<Vec<SystemParam>> {
+        T5Client::get_changed_system_params(self)
+    }
+}
+
+/// Stand-in viewable extents for each kind of board, for backends without a
+/// service to ask - the same ones the fake native library reports
+pub(crate) fn nominal_gameboard_size(gameboard_type: T5GameboardType) -> T5_GameboardSize {
+    let (x, y, z) = match gameboard_type {
+        T5GameboardType::None => (0., 0., 0.),
+        T5GameboardType::LE => (0.35, 0.35, 0.),
+        T5GameboardType::XE => (0.7, 0.35, 0.),
+        T5GameboardType::XeRaised => (0.7, 0.35, 0.5),
+    };
+    T5_GameboardSize {
+        viewableExtentPositiveX: x,
+        viewableExtentNegativeX: x,
+        viewableExtentPositiveY: y,
+        viewableExtentNegativeY: y,
+        viewableExtentPositiveZ: z,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_app<B: TiltFiveBackend + Clone + Send + Sync + 'static>(
+    backend: B,
+) -> bevy::prelude::App {
+    test_app_with_settings(backend, Default::default())
+}
+
+#[cfg(test)]
+pub(crate) fn test_app_with_settings<B: TiltFiveBackend + Clone + Send + Sync + 'static>(
+    backend: B,
+    settings: crate::TiltFiveSettings,
+) -> bevy::prelude::App {
+    use bevy::prelude::*;
+
+    let plugin = crate::TiltFivePlugin::with_backend(settings, move || Ok(backend.clone()));
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Image>()
+        .add_asset::<Mesh>()
+        .add_asset::<StandardMaterial>()
+        .add_plugin(plugin);
+    app.world.spawn(crate::BoardBundle::default());
+    app
+}
+
+/// An app already connected to a fresh pair of fake glasses, returning the
+/// backend to script them with and their entity
+#[cfg(test)]
+pub(crate) fn connected_app(
+    id: &str,
+) -> (
+    bevy::prelude::App,
+    FakeBackend,
+    Glasses,
+    bevy::prelude::Entity,
+) {
+    let backend = FakeBackend::new();
+    let glasses: Glasses = id.into();
+    backend.add_glasses(glasses.clone(), None);
+    let mut app = test_app(backend.clone());
+    let entity = connect(&mut app, &glasses);
+    (app, backend, glasses, entity)
+}
+
+/// Refresh the glasses list and connect to the glasses, returning their entity
+#[cfg(test)]
+pub(crate) fn connect(app: &mut bevy::prelude::App, glasses: &Glasses) -> bevy::prelude::Entity {
+    use crate::TiltFiveCommands;
+
+    app.world.send_event(TiltFiveCommands::RefreshGlassesList);
+    run_frames(app);
+    app.world
+        .send_event(TiltFiveCommands::ConnectToGlasses(glasses.clone()));
+    run_frames(app);
+    connected_entity(app, glasses).expect("glasses should be connected")
+}
+
+/// The entity of the glasses, while they're connected
+#[cfg(test)]
+pub(crate) fn connected_entity(
+    app: &bevy::prelude::App,
+    glasses: &Glasses,
+) -> Option<bevy::prelude::Entity> {
+    match app
+        .world
+        .resource::<crate::AvailableGlasses>()
+        .glasses
+        .get(glasses)
+    {
+        Some(crate::GlassesInfo::Connected { entity, .. }) => Some(*entity),
+        _ => None,
+    }
+}
+
+/// Commands and events take a few frames to round trip through the client
+#[cfg(test)]
+pub(crate) fn run_frames(app: &mut bevy::prelude::App) {
+    for _ in 0..5 {
+        app.update();
+        poll_worker(app);
+    }
+}
+
+/// Have the worker poll between frames, and wait for it to
+#[cfg(test)]
+pub(crate) fn poll_worker(app: &bevy::prelude::App) {
+    if let Some(client) = app.world.get_non_send_resource::<crate::T5ClientMainApp>() {
+        crate::worker::poll_now(&client.sender);
+    }
+}
+
+/// Run a few frames, collecting the client events sent during them
+#[cfg(test)]
+pub(crate) fn client_events(app: &mut bevy::prelude::App) -> Vec<crate::TiltFiveClientEvent> {
+    // Skips events an earlier call already collected, which stay around
+    // for a frame
+    let mut reader = app
+        .world
+        .resource::<bevy::prelude::Events<crate::TiltFiveClientEvent>>()
+        .get_reader_current();
+    let mut events = vec![];
+    for _ in 0..4 {
+        app.update();
+        poll_worker(app);
+        events.extend(
+            reader
+                .iter(
+                    app.world
+                        .resource::<bevy::prelude::Events<crate::TiltFiveClientEvent>>(),
+                )
+                .cloned(),
+        );
+    }
+    events
+}
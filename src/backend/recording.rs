@@ -0,0 +1,1077 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{log::warn, utils::HashMap};
+
+use crate::bridge::{
+    ffi::{
+        T5_ConnectionState, T5_FrameInfo, T5_GameboardSize, T5_GameboardType, T5_GlassesPose,
+        T5_Hand, T5_Quat, T5_Vec2, T5_Vec3, T5_WandHandle, T5_WandReport,
+        T5_WandReport__bindgen_ty_1, T5_WandStreamEvent, T5_WandStreamEventType,
+        T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect,
+    },
+    CameraImage, ConnectionProgress, Glasses, GlassesCamera, GlassesConnectionState, GlassesParam,
+    SystemParam, T5Error, T5GameboardType, T5Result,
+};
+
+use super::TiltFiveBackend;
+
+const MAGIC: &[u8; 8] = b"T5TRACK\0";
+
+/// The version of the on-disk format written by `Recording::write_to`.
+///
+/// Bump this whenever the layout of a sample changes - older files are
+/// rejected rather than misread.
+pub const RECORDING_VERSION: u16 = 4;
+
+/// A tracking session captured by a `RecordingBackend`.
+///
+/// Frames are in the order they were recorded, and their `elapsed` times are
+/// measured from the first successful connection to a pair of glasses.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedFrame {
+    pub elapsed: Duration,
+    pub glasses: Glasses,
+    pub sample: RecordedSample,
+}
+
+/// Something the SDK handed to the plugin
+#[derive(Clone, Debug)]
+pub enum RecordedSample {
+    Pose(T5_GlassesPose),
+    /// In millimeters, like the SDK reports it
+    Ipd(f32),
+    FriendlyName(Option<String>),
+    WandEvent(T5_WandStreamEvent),
+    ConnectionState(GlassesConnectionState),
+    CameraImage(CameraImage),
+    /// The smoothed pose for spectator views
+    SpectatorPose(T5_GlassesPose),
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Read a recording, dropping a truncated final frame - which is what a
+    /// session that crashed mid-write leaves behind.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        read_header(&mut reader)?;
+        let mut frames = vec![];
+        loop {
+            match RecordedFrame::read(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Self { frames })
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_header(&mut writer)?;
+        for frame in self.frames.iter() {
+            frame.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// Every pair of glasses that appears in the recording
+    pub fn glasses(&self) -> Vec<Glasses> {
+        let mut glasses: Vec<Glasses> = vec![];
+        for frame in self.frames.iter() {
+            if !glasses.contains(&frame.glasses) {
+                glasses.push(frame.glasses.clone());
+            }
+        }
+        glasses
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map(|frame| frame.elapsed)
+            .unwrap_or_default()
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&RECORDING_VERSION.to_le_bytes())
+}
+
+fn read_header<R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a Tilt Five tracking recording"));
+    }
+    let version = read_u16(reader)?;
+    if version != RECORDING_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported recording version {version}, expected {RECORDING_VERSION}"
+        )));
+    }
+    Ok(())
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+const POSE: u8 = 0;
+const IPD: u8 = 1;
+const FRIENDLY_NAME: u8 = 2;
+const WAND_EVENT: u8 = 3;
+const CONNECTION_STATE: u8 = 4;
+const CAMERA_IMAGE: u8 = 5;
+const SPECTATOR_POSE: u8 = 6;
+
+impl RecordedFrame {
+    // The SDK enums are only `u32` on some platforms
+    #[allow(clippy::unnecessary_cast)]
+    fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_u64(w, self.elapsed.as_nanos() as u64)?;
+        write_string(w, (&self.glasses).into())?;
+        match &self.sample {
+            RecordedSample::Pose(pose) => {
+                write_u8(w, POSE)?;
+                write_pose(w, pose)
+            }
+            RecordedSample::Ipd(ipd) => {
+                write_u8(w, IPD)?;
+                write_f32(w, *ipd)
+            }
+            RecordedSample::FriendlyName(name) => {
+                write_u8(w, FRIENDLY_NAME)?;
+                match name {
+                    Some(name) => {
+                        write_u8(w, 1)?;
+                        write_string(w, name)
+                    }
+                    None => write_u8(w, 0),
+                }
+            }
+            RecordedSample::WandEvent(event) => {
+                write_u8(w, WAND_EVENT)?;
+                write_u8(w, event.wandId)?;
+                write_u32(w, event.type_ as u32)?;
+                write_u64(w, event.timestampNanos)?;
+                write_wand_report(w, &event.report)
+            }
+            RecordedSample::ConnectionState(state) => {
+                write_u8(w, CONNECTION_STATE)?;
+                write_u32(w, T5_ConnectionState::from(*state) as u32)
+            }
+            RecordedSample::CameraImage(image) => {
+                write_u8(w, CAMERA_IMAGE)?;
+                write_u8(w, image.camera.into())?;
+                write_u8(w, image.illumination.into())?;
+                write_u32(w, image.width)?;
+                write_u32(w, image.height)?;
+                write_vec3(w, &image.position.into())?;
+                write_quat(w, &image.rotation.into())?;
+                write_bytes(w, &image.pixels)
+            }
+            RecordedSample::SpectatorPose(pose) => {
+                write_u8(w, SPECTATOR_POSE)?;
+                write_pose(w, pose)
+            }
+        }
+    }
+
+    fn read<R: Read>(r: &mut R) -> io::Result<Self> {
+        let elapsed = Duration::from_nanos(read_u64(r)?);
+        let glasses = read_string(r)?.into();
+        let sample = match read_u8(r)? {
+            POSE => RecordedSample::Pose(read_pose(r)?),
+            IPD => RecordedSample::Ipd(read_f32(r)?),
+            FRIENDLY_NAME => RecordedSample::FriendlyName(match read_u8(r)? {
+                0 => None,
+                _ => Some(read_string(r)?),
+            }),
+            WAND_EVENT => RecordedSample::WandEvent(T5_WandStreamEvent {
+                wandId: read_u8(r)?,
+                type_: read_u32(r)? as T5_WandStreamEventType,
+                timestampNanos: read_u64(r)?,
+                report: read_wand_report(r)?,
+            }),
+            CONNECTION_STATE => {
+                RecordedSample::ConnectionState((read_u32(r)? as T5_ConnectionState).into())
+            }
+            CAMERA_IMAGE => RecordedSample::CameraImage(CameraImage {
+                camera: GlassesCamera::try_from(read_u8(r)?)
+                    .map_err(|_| invalid_data("unknown camera"))?,
+                illumination: read_u8(r)?.into(),
+                width: read_u32(r)?,
+                height: read_u32(r)?,
+                position: read_vec3(r)?.into(),
+                rotation: read_quat(r)?.into(),
+                pixels: read_bytes(r)?,
+            }),
+            SPECTATOR_POSE => RecordedSample::SpectatorPose(read_pose(r)?),
+            tag => return Err(invalid_data(format!("unknown sample type {tag}"))),
+        };
+        Ok(Self {
+            elapsed,
+            glasses,
+            sample,
+        })
+    }
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn write_pose<W: Write>(w: &mut W, pose: &T5_GlassesPose) -> io::Result<()> {
+    write_u64(w, pose.timestampNanos)?;
+    write_vec3(w, &pose.posGLS_GBD)?;
+    write_quat(w, &pose.rotToGLS_GBD)?;
+    write_u32(w, pose.gameboardType as u32)
+}
+
+fn read_pose<R: Read>(r: &mut R) -> io::Result<T5_GlassesPose> {
+    Ok(T5_GlassesPose {
+        timestampNanos: read_u64(r)?,
+        posGLS_GBD: read_vec3(r)?,
+        rotToGLS_GBD: read_quat(r)?,
+        gameboardType: read_u32(r)? as T5_GameboardType,
+    })
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn write_wand_report<W: Write>(w: &mut W, report: &T5_WandReport) -> io::Result<()> {
+    let buttons = &report.buttons;
+    write_u64(w, report.timestampNanos)?;
+    write_bits(
+        w,
+        [
+            report.analogValid,
+            report.batteryValid,
+            report.buttonsValid,
+            report.poseValid,
+        ],
+    )?;
+    write_f32(w, report.trigger)?;
+    write_f32(w, report.stick.x)?;
+    write_f32(w, report.stick.y)?;
+    write_u8(w, report.battery)?;
+    write_bits(
+        w,
+        [
+            buttons.t5,
+            buttons.one,
+            buttons.two,
+            buttons.three,
+            buttons.a,
+            buttons.b,
+            buttons.x,
+            buttons.y,
+        ],
+    )?;
+    write_quat(w, &report.rotToWND_GBD)?;
+    write_vec3(w, &report.posAim_GBD)?;
+    write_vec3(w, &report.posFingertips_GBD)?;
+    write_vec3(w, &report.posGrip_GBD)?;
+    write_u32(w, report.hand as u32)
+}
+
+fn read_wand_report<R: Read>(r: &mut R) -> io::Result<T5_WandReport> {
+    let timestamp_nanos = read_u64(r)?;
+    let [analog_valid, battery_valid, buttons_valid, pose_valid] = read_bits(r)?;
+    let trigger = read_f32(r)?;
+    let stick = T5_Vec2 {
+        x: read_f32(r)?,
+        y: read_f32(r)?,
+    };
+    let battery = read_u8(r)?;
+    let [t5, one, two, three, a, b, x, y] = read_bits(r)?;
+    Ok(T5_WandReport {
+        timestampNanos: timestamp_nanos,
+        analogValid: analog_valid,
+        batteryValid: battery_valid,
+        buttonsValid: buttons_valid,
+        poseValid: pose_valid,
+        trigger,
+        stick,
+        battery,
+        buttons: T5_WandReport__bindgen_ty_1 {
+            t5,
+            one,
+            two,
+            three,
+            a,
+            b,
+            x,
+            y,
+        },
+        rotToWND_GBD: read_quat(r)?,
+        posAim_GBD: read_vec3(r)?,
+        posFingertips_GBD: read_vec3(r)?,
+        posGrip_GBD: read_vec3(r)?,
+        hand: read_u32(r)? as T5_Hand,
+    })
+}
+
+fn write_u8<W: Write>(w: &mut W, value: u8) -> io::Result<()> {
+    w.write_all(&[value])
+}
+
+fn write_u32<W: Write>(w: &mut W, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_u64<W: Write>(w: &mut W, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_f32<W: Write>(w: &mut W, value: f32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_bits<W: Write, const N: usize>(w: &mut W, bits: [bool; N]) -> io::Result<()> {
+    let byte = bits
+        .iter()
+        .enumerate()
+        .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << i));
+    write_u8(w, byte)
+}
+
+fn write_string<W: Write>(w: &mut W, value: &str) -> io::Result<()> {
+    write_bytes(w, value.as_bytes())
+}
+
+fn write_bytes<W: Write>(w: &mut W, value: &[u8]) -> io::Result<()> {
+    write_u32(w, value.len() as u32)?;
+    w.write_all(value)
+}
+
+fn write_vec3<W: Write>(w: &mut W, value: &T5_Vec3) -> io::Result<()> {
+    write_f32(w, value.x)?;
+    write_f32(w, value.y)?;
+    write_f32(w, value.z)
+}
+
+fn write_quat<W: Write>(w: &mut W, value: &T5_Quat) -> io::Result<()> {
+    write_f32(w, value.w)?;
+    write_f32(w, value.x)?;
+    write_f32(w, value.y)?;
+    write_f32(w, value.z)
+}
+
+fn read_array<R: Read, const N: usize>(r: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    Ok(read_array::<_, 1>(r)?[0])
+}
+
+fn read_u16<R: Read>(r: &mut R) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(read_array(r)?))
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> io::Result<f32> {
+    Ok(f32::from_le_bytes(read_array(r)?))
+}
+
+fn read_bits<R: Read, const N: usize>(r: &mut R) -> io::Result<[bool; N]> {
+    let byte = read_u8(r)?;
+    let mut bits = [false; N];
+    for (i, bit) in bits.iter_mut().enumerate() {
+        *bit = byte & (1 << i) != 0;
+    }
+    Ok(bits)
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    String::from_utf8(read_bytes(r)?).map_err(invalid_data)
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_u32(r)? as usize;
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_vec3<R: Read>(r: &mut R) -> io::Result<T5_Vec3> {
+    Ok(T5_Vec3 {
+        x: read_f32(r)?,
+        y: read_f32(r)?,
+        z: read_f32(r)?,
+    })
+}
+
+fn read_quat<R: Read>(r: &mut R) -> io::Result<T5_Quat> {
+    Ok(T5_Quat {
+        w: read_f32(r)?,
+        x: read_f32(r)?,
+        y: read_f32(r)?,
+        z: read_f32(r)?,
+    })
+}
+
+/// The last value recorded for each pair of glasses, so a sample is only
+/// written when the SDK gives us something new.
+#[derive(Default)]
+struct LastRecorded {
+    pose_timestamp: Option<u64>,
+    spectator_pose_timestamp: Option<u64>,
+    ipd: Option<f32>,
+    friendly_name: Option<Option<String>>,
+    connection_state: Option<GlassesConnectionState>,
+}
+
+/// Wraps another backend, streaming everything it hands to the plugin into a
+/// recording that a `ReplayBackend` can play back later.
+pub struct RecordingBackend<B, W: Write = BufWriter<File>> {
+    inner: B,
+    /// Dropped after the first write error, so a full disk doesn't stop tracking
+    writer: Option<W>,
+    start: Option<Instant>,
+    last: HashMap<Glasses, LastRecorded>,
+}
+
+impl<B: TiltFiveBackend> RecordingBackend<B> {
+    /// Record everything `inner` reports to a new file at `path`
+    pub fn create<P: AsRef<Path>>(inner: B, path: P) -> io::Result<Self> {
+        Self::new(inner, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<B: TiltFiveBackend, W: Write> RecordingBackend<B, W> {
+    pub fn new(inner: B, mut writer: W) -> io::Result<Self> {
+        write_header(&mut writer)?;
+        Ok(Self {
+            inner,
+            writer: Some(writer),
+            start: None,
+            last: Default::default(),
+        })
+    }
+
+    /// Stop recording, handing back the wrapped backend and the flushed writer
+    pub fn finish(mut self) -> io::Result<(B, Option<W>)> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok((self.inner, self.writer))
+    }
+
+    fn record(&mut self, glasses: &Glasses, sample: RecordedSample) {
+        if let Some(start) = self.start {
+            self.write_frame(start.elapsed(), glasses, sample);
+        }
+    }
+
+    fn write_frame(&mut self, elapsed: Duration, glasses: &Glasses, sample: RecordedSample) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let frame = RecordedFrame {
+            elapsed,
+            glasses: glasses.clone(),
+            sample,
+        };
+        if let Err(err) = frame.write(writer) {
+            warn!("Stopped recording tracking session: {err}");
+            self.writer = None;
+        }
+    }
+
+    fn name_changed(&mut self, glasses: &Glasses, friendly_name: &Option<String>) -> bool {
+        let last = self.last.entry(glasses.clone()).or_default();
+        let changed = last.friendly_name.as_ref() != Some(friendly_name);
+        last.friendly_name = Some(friendly_name.clone());
+        changed
+    }
+}
+
+impl<B: TiltFiveBackend, W: Write> TiltFiveBackend for RecordingBackend<B, W> {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
+        self.inner.list_glasses()
+    }
+
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress> {
+        let progress = self.inner.connect_glasses(glasses)?;
+        if progress != ConnectionProgress::Ready {
+            return Ok(progress);
+        }
+        // The session starts with the first connection, so anything reported
+        // while connecting to those glasses is at time zero
+        let elapsed = match self.start {
+            Some(start) => start.elapsed(),
+            None => {
+                self.start = Some(Instant::now());
+                Duration::ZERO
+            }
+        };
+        let friendly_name = self
+            .inner
+            .get_glasses_name(glasses)
+            .ok()
+            .filter(|name| !name.is_empty());
+        if self.name_changed(glasses, &friendly_name) {
+            let sample = RecordedSample::FriendlyName(friendly_name);
+            self.write_frame(elapsed, glasses, sample);
+        }
+        Ok(progress)
+    }
+
+    fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()> {
+        self.last.remove(&glasses);
+        self.inner.release_glasses(glasses)
+    }
+
+    fn set_glasses_display_name(&mut self, glasses: &Glasses, display_name: &str) -> T5Result<()> {
+        self.inner.set_glasses_display_name(glasses, display_name)
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        let pose = self.inner.get_glasses_pose(glasses)?;
+        let last = self.last.entry(glasses.clone()).or_default();
+        if last.pose_timestamp != Some(pose.timestampNanos) {
+            last.pose_timestamp = Some(pose.timestampNanos);
+            self.record(glasses, RecordedSample::Pose(pose));
+        }
+        Ok(pose)
+    }
+
+    fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        let pose = self.inner.get_spectator_pose(glasses)?;
+        let last = self.last.entry(glasses.clone()).or_default();
+        if last.spectator_pose_timestamp != Some(pose.timestampNanos) {
+            last.spectator_pose_timestamp = Some(pose.timestampNanos);
+            self.record(glasses, RecordedSample::SpectatorPose(pose));
+        }
+        Ok(pose)
+    }
+
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState> {
+        let state = self.inner.get_connection_state(glasses)?;
+        let last = self.last.entry(glasses.clone()).or_default();
+        if last.connection_state != Some(state) {
+            last.connection_state = Some(state);
+            self.record(glasses, RecordedSample::ConnectionState(state));
+        }
+        Ok(state)
+    }
+
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32> {
+        let ipd = self.inner.get_ipd(glasses)?;
+        let last = self.last.entry(glasses.clone()).or_default();
+        if last.ipd != Some(ipd) {
+            last.ipd = Some(ipd);
+            self.record(glasses, RecordedSample::Ipd(ipd));
+        }
+        Ok(ipd)
+    }
+
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String> {
+        let name = self.inner.get_glasses_name(glasses)?;
+        let friendly_name = (!name.is_empty()).then(|| name.clone());
+        if self.name_changed(glasses, &friendly_name) {
+            self.record(glasses, RecordedSample::FriendlyName(friendly_name));
+        }
+        Ok(name)
+    }
+
+    fn get_changed_glasses_params(&mut self, glasses: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        self.inner.get_changed_glasses_params(glasses)
+    }
+
+    fn list_wands(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        self.inner.list_wands(glasses)
+    }
+
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>> {
+        let events = self.inner.get_wand_stream_events(glasses)?;
+        for event in events.iter() {
+            self.record(glasses, RecordedSample::WandEvent(*event));
+        }
+        Ok(events)
+    }
+
+    fn configure_camera_stream(
+        &mut self,
+        glasses: &Glasses,
+        camera: GlassesCamera,
+        enabled: bool,
+    ) -> T5Result<()> {
+        self.inner.configure_camera_stream(glasses, camera, enabled)
+    }
+
+    fn get_camera_images(&mut self, glasses: &Glasses) -> T5Result<Vec<CameraImage>> {
+        let images = self.inner.get_camera_images(glasses)?;
+        for image in images.iter() {
+            self.record(glasses, RecordedSample::CameraImage(image.clone()));
+        }
+        Ok(images)
+    }
+
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        info: *const T5_FrameInfo,
+    ) -> T5Result<()> {
+        self.inner.send_frame_to_glasses(glasses, info)
+    }
+
+    unsafe fn set_dx11_graphics_context(&mut self, device: *mut std::ffi::c_void) -> T5Result<()> {
+        self.inner.set_dx11_graphics_context(device)
+    }
+
+    fn get_gameboard_size(
+        &mut self,
+        gameboard_type: T5GameboardType,
+    ) -> T5Result<T5_GameboardSize> {
+        self.inner.get_gameboard_size(gameboard_type)
+    }
+
+    fn get_service_version(&mut self) -> T5Result<String> {
+        self.inner.get_service_version()
+    }
+
+    fn is_attention_required(&mut self) -> T5Result<bool> {
+        self.inner.is_attention_required()
+    }
+
+    fn get_changed_system_params(&mut self) -> T5Result<Vec<SystemParam>> {
+        self.inner.get_changed_system_params()
+    }
+}
+
+/// Plays a `Recording` back to the plugin at the timing it was recorded with.
+///
+/// Like the `FakeBackend`, clones share their state - so a test can hold on to
+/// one and step a manual clock while the plugin owns another.
+#[derive(Clone)]
+pub struct ReplayBackend {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+struct ReplayState {
+    recording: Recording,
+    clock: ReplayClock,
+    next_frame: usize,
+    glasses: HashMap<Glasses, ReplayedGlasses>,
+}
+
+enum ReplayClock {
+    /// Starts running when the first glasses connect, like the recording did
+    Realtime {
+        started: Option<Instant>,
+        skipped: Duration,
+    },
+    Manual(Duration),
+}
+
+impl ReplayClock {
+    fn now(&self) -> Duration {
+        match self {
+            ReplayClock::Realtime { started, skipped } => {
+                started.map(|s| s.elapsed()).unwrap_or_default() + *skipped
+            }
+            ReplayClock::Manual(now) => *now,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReplayedGlasses {
+    connected: bool,
+    pose: Option<T5_GlassesPose>,
+    spectator_pose: Option<T5_GlassesPose>,
+    ipd: Option<f32>,
+    friendly_name: Option<String>,
+    connection_state: Option<GlassesConnectionState>,
+    changed_params: Vec<GlassesParam>,
+    wand_events: Vec<T5_WandStreamEvent>,
+    /// The wands seen on the wand stream so far, and not since disconnected
+    wands: Vec<T5_WandHandle>,
+    camera_streams: Vec<GlassesCamera>,
+    camera_images: Vec<CameraImage>,
+}
+
+impl ReplayBackend {
+    pub fn new(recording: Recording) -> Self {
+        let glasses = recording
+            .glasses()
+            .into_iter()
+            .map(|glasses| (glasses, Default::default()))
+            .collect();
+        Self {
+            state: Arc::new(Mutex::new(ReplayState {
+                recording,
+                clock: ReplayClock::Realtime {
+                    started: None,
+                    skipped: Duration::ZERO,
+                },
+                next_frame: 0,
+                glasses,
+            })),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(Recording::load(path)?))
+    }
+
+    /// Only move through the recording when `advance` is called, for
+    /// deterministic tests
+    pub fn with_manual_clock(self) -> Self {
+        self.state.lock().unwrap().clock = ReplayClock::Manual(Duration::ZERO);
+        self
+    }
+
+    /// Skip ahead in the recording
+    pub fn advance(&self, by: Duration) -> &Self {
+        match &mut self.state.lock().unwrap().clock {
+            ReplayClock::Realtime { skipped, .. } => *skipped += by,
+            ReplayClock::Manual(now) => *now += by,
+        }
+        self
+    }
+
+    /// Whether every recorded frame has been played back
+    pub fn is_finished(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.catch_up();
+        state.next_frame >= state.recording.frames.len()
+    }
+
+    fn connected<R>(
+        &self,
+        glasses: &Glasses,
+        f: impl FnOnce(&mut ReplayedGlasses) -> T5Result<R>,
+    ) -> T5Result<R> {
+        let mut state = self.state.lock().unwrap();
+        state.catch_up();
+        match state.glasses.get_mut(glasses) {
+            Some(g) if g.connection_state == Some(GlassesConnectionState::Disconnected) => {
+                Err(T5Error::DeviceLost)
+            }
+            Some(g) if g.connected => f(g),
+            Some(_) => Err(T5Error::NotConnected),
+            None => Err(T5Error::GlassesNotFound(glasses.to_string())),
+        }
+    }
+}
+
+impl ReplayState {
+    /// Apply every recorded frame up to the current time
+    fn catch_up(&mut self) {
+        let now = self.clock.now();
+        while let Some(frame) = self.recording.frames.get(self.next_frame) {
+            if frame.elapsed > now {
+                break;
+            }
+            self.next_frame += 1;
+            let Some(glasses) = self.glasses.get_mut(&frame.glasses) else {
+                continue;
+            };
+            match &frame.sample {
+                RecordedSample::Pose(pose) => glasses.pose = Some(*pose),
+                RecordedSample::SpectatorPose(pose) => glasses.spectator_pose = Some(*pose),
+                RecordedSample::Ipd(ipd) => {
+                    glasses.ipd = Some(*ipd);
+                    glasses.changed_params.push(GlassesParam::Ipd);
+                }
+                RecordedSample::FriendlyName(name) => {
+                    glasses.friendly_name = name.clone();
+                    glasses.changed_params.push(GlassesParam::FriendlyName);
+                }
+                RecordedSample::WandEvent(event) => {
+                    if event.type_ == T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect {
+                        glasses.wands.retain(|wand| *wand != event.wandId);
+                    } else if !glasses.wands.contains(&event.wandId) {
+                        glasses.wands.push(event.wandId);
+                    }
+                    glasses.wand_events.push(*event);
+                }
+                RecordedSample::ConnectionState(state) => glasses.connection_state = Some(*state),
+                // Like the service, frames only arrive while their camera is streaming
+                RecordedSample::CameraImage(image) => {
+                    if glasses.camera_streams.contains(&image.camera) {
+                        glasses.camera_images.push(image.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TiltFiveBackend for ReplayBackend {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
+        Ok(self.state.lock().unwrap().recording.glasses())
+    }
+
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress> {
+        let mut state = self.state.lock().unwrap();
+        if let ReplayClock::Realtime { started, .. } = &mut state.clock {
+            started.get_or_insert_with(Instant::now);
+        }
+        state.catch_up();
+        match state.glasses.get_mut(glasses) {
+            Some(g) if g.connection_state == Some(GlassesConnectionState::Disconnected) => {
+                Err(T5Error::DeviceLost)
+            }
+            Some(g) => {
+                g.connected = true;
+                Ok(ConnectionProgress::Ready)
+            }
+            None => Err(T5Error::GlassesNotFound(glasses.to_string())),
+        }
+    }
+
+    fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()> {
+        if let Some(g) = self.state.lock().unwrap().glasses.get_mut(&glasses) {
+            g.connected = false;
+            g.camera_streams.clear();
+        }
+        Ok(())
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.connected(glasses, |g| g.pose.ok_or(T5Error::TryAgain))
+    }
+
+    /// Recordings made before spectator poses were recorded fall back to the
+    /// glasses pose
+    fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.connected(glasses, |g| {
+            g.spectator_pose.or(g.pose).ok_or(T5Error::TryAgain)
+        })
+    }
+
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState> {
+        let mut state = self.state.lock().unwrap();
+        state.catch_up();
+        match state.glasses.get(glasses) {
+            Some(ReplayedGlasses {
+                connected: true,
+                connection_state,
+                ..
+            }) => Ok(connection_state.unwrap_or(GlassesConnectionState::ExclusiveConnection)),
+            Some(_) => Ok(GlassesConnectionState::NotExclusivelyConnected),
+            None => Err(T5Error::GlassesNotFound(glasses.to_string())),
+        }
+    }
+
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32> {
+        self.connected(glasses, |g| g.ipd.ok_or(T5Error::TryAgain))
+    }
+
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String> {
+        self.connected(glasses, |g| Ok(g.friendly_name.clone().unwrap_or_default()))
+    }
+
+    fn get_changed_glasses_params(&mut self, glasses: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        self.connected(glasses, |g| Ok(std::mem::take(&mut g.changed_params)))
+    }
+
+    /// Wands connected before the recording started only show up once they
+    /// send a report
+    fn list_wands(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        self.connected(glasses, |g| Ok(g.wands.clone()))
+    }
+
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>> {
+        self.connected(glasses, |g| Ok(std::mem::take(&mut g.wand_events)))
+    }
+
+    fn configure_camera_stream(
+        &mut self,
+        glasses: &Glasses,
+        camera: GlassesCamera,
+        enabled: bool,
+    ) -> T5Result<()> {
+        self.connected(glasses, |g| {
+            g.camera_streams.retain(|c| *c != camera);
+            if enabled {
+                g.camera_streams.push(camera);
+            }
+            Ok(())
+        })
+    }
+
+    fn get_camera_images(&mut self, glasses: &Glasses) -> T5Result<Vec<CameraImage>> {
+        self.connected(glasses, |g| Ok(std::mem::take(&mut g.camera_images)))
+    }
+
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        _info: *const T5_FrameInfo,
+    ) -> T5Result<()> {
+        self.connected(glasses, |_| Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        backend::{connect, run_frames, test_app, FakeBackend},
+        bridge::{ffi::T5_WandStreamEventType_kT5_WandStreamEventType_Connect, IlluminationMode},
+        AvailableGlasses, GlassesInfo,
+    };
+
+    use super::*;
+
+    fn recorded_session(glasses: &Glasses) -> Vec<u8> {
+        let fake = FakeBackend::new();
+        fake.add_glasses(glasses.clone(), Some("Recorded"));
+        let mut recorder = RecordingBackend::new(fake.clone(), vec![]).unwrap();
+
+        while recorder.connect_glasses(glasses).unwrap() != ConnectionProgress::Ready {}
+        fake.set_pose(glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        recorder.get_glasses_pose(glasses).unwrap();
+        // The same pose again shouldn't be recorded twice
+        recorder.get_glasses_pose(glasses).unwrap();
+        recorder.get_ipd(glasses).unwrap();
+
+        std::thread::sleep(Duration::from_millis(20));
+        fake.set_pose(glasses, Vec3::new(1., 0., 1.), Quat::IDENTITY);
+        recorder.get_glasses_pose(glasses).unwrap();
+        let mut event = T5_WandStreamEvent {
+            wandId: 1,
+            type_: T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+            timestampNanos: 5,
+            report: T5_WandReport::default(),
+        };
+        event.report.buttons.a = true;
+        event.report.trigger = 0.5;
+        fake.push_wand_event(glasses, event);
+        recorder.get_wand_stream_events(glasses).unwrap();
+        recorder.get_connection_state(glasses).unwrap();
+        // Only changes in the connection state are recorded
+        recorder.get_connection_state(glasses).unwrap();
+        recorder
+            .configure_camera_stream(glasses, GlassesCamera::Tangible, true)
+            .unwrap();
+        fake.push_camera_image(
+            glasses,
+            CameraImage {
+                camera: GlassesCamera::Tangible,
+                illumination: IlluminationMode::Light,
+                width: 2,
+                height: 2,
+                pixels: vec![0, 64, 128, 255],
+                position: Vec3::new(0., 0., 1.),
+                rotation: Quat::IDENTITY,
+            },
+        );
+        recorder.get_camera_images(glasses).unwrap();
+        fake.set_spectator_pose(glasses, Vec3::new(0., 0.5, 1.), Quat::IDENTITY);
+        recorder.get_spectator_pose(glasses).unwrap();
+        recorder.get_spectator_pose(glasses).unwrap();
+
+        let (_, writer) = recorder.finish().unwrap();
+        writer.unwrap()
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let glasses: Glasses = "recorded-glasses".into();
+        let bytes = recorded_session(&glasses);
+        let recording = Recording::read_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(recording.glasses(), vec![glasses.clone()]);
+        assert_eq!(recording.frames.len(), 8);
+        assert!(matches!(
+            &recording.frames[0].sample,
+            RecordedSample::FriendlyName(Some(name)) if name == "Recorded"
+        ));
+        assert!(matches!(
+            recording.frames[1].sample,
+            RecordedSample::Pose(pose) if pose.posGLS_GBD.z == 1.
+        ));
+        assert!(matches!(recording.frames[2].sample, RecordedSample::Ipd(ipd) if ipd == 59.));
+        assert!(recording.frames[3].elapsed >= Duration::from_millis(20));
+        assert!(matches!(
+            recording.frames[4].sample,
+            RecordedSample::WandEvent(event)
+                if event.wandId == 1 && event.report.buttons.a && !event.report.buttons.b
+                    && event.report.trigger == 0.5
+        ));
+        assert!(matches!(
+            recording.frames[5].sample,
+            RecordedSample::ConnectionState(GlassesConnectionState::ExclusiveConnection)
+        ));
+        assert!(matches!(
+            &recording.frames[6].sample,
+            RecordedSample::CameraImage(image)
+                if image.illumination == IlluminationMode::Light
+                    && image.pixel(1, 1) == 255 && image.position.z == 1.
+        ));
+        assert!(matches!(
+            recording.frames[7].sample,
+            RecordedSample::SpectatorPose(pose) if pose.posGLS_GBD.y == 0.5
+        ));
+
+        // Dropping part of the last frame, as a crash would, only loses that frame
+        let truncated = Recording::read_from(&bytes[..bytes.len() - 3]).unwrap();
+        assert_eq!(truncated.frames.len(), 7);
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[MAGIC.len()] += 1;
+        let err = Recording::read_from(wrong_version.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replays_at_recorded_timing() {
+        let glasses: Glasses = "recorded-glasses".into();
+        let recording = Recording::read_from(recorded_session(&glasses).as_slice()).unwrap();
+        let tracked_at = recording.frames[2].elapsed;
+        let finished_at = recording.duration();
+        let replay = ReplayBackend::new(recording).with_manual_clock();
+        let mut app = test_app(replay.clone());
+        let entity = connect(&mut app, &glasses);
+        assert!(matches!(
+            app.world.resource::<AvailableGlasses>().glasses.get(&glasses),
+            Some(GlassesInfo::Connected { friendly_name: Some(name), .. }) if name == "Recorded"
+        ));
+        let transform = app.world.get::<Transform>(entity).unwrap();
+        assert_eq!(transform.translation, Vec3::ZERO);
+
+        replay.advance(tracked_at);
+        run_frames(&mut app);
+        let transform = app.world.get::<Transform>(entity).unwrap();
+        assert!((transform.translation - Vec3::new(0., 1., 0.)).length() < 1e-4);
+
+        replay.advance(finished_at - tracked_at);
+        run_frames(&mut app);
+        let transform = app.world.get::<Transform>(entity).unwrap();
+        assert!((transform.translation - Vec3::new(1., 1., 0.)).length() < 1e-4);
+        assert!(replay.is_finished());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    log::warn,
+    prelude::{Quat, Vec3},
+    reflect::{FromReflect, Reflect},
+};
+
+use super::{ffi::*, op, Glasses, T5Client, T5Error, T5Result};
+
+/// The size of the buffers handed to the service - big enough for any frame
+const CAMERA_BUFFER_SIZE: usize =
+    (T5_MIN_CAM_IMAGE_BUFFER_WIDTH * T5_MIN_CAM_IMAGE_BUFFER_HEIGHT) as usize;
+
+/// The cameras on a pair of glasses that can be streamed from
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlassesCamera {
+    /// The camera used to track the board and tangible objects
+    Tangible,
+    /// The camera used to track the glasses themselves
+    HeadTracking,
+}
+
+impl From<GlassesCamera> for u8 {
+    fn from(value: GlassesCamera) -> Self {
+        match value {
+            GlassesCamera::Tangible => 0,
+            GlassesCamera::HeadTracking => 1,
+        }
+    }
+}
+
+impl TryFrom<u8> for GlassesCamera {
+    type Error = T5Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(GlassesCamera::Tangible),
+            1 => Ok(GlassesCamera::HeadTracking),
+            _ => Err(T5Error::InvalidArgs),
+        }
+    }
+}
+
+/// Whether the glasses lit the scene with their IR illuminator for a frame
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IlluminationMode {
+    #[default]
+    Unknown,
+    Light,
+    Dark,
+}
+
+impl From<u8> for IlluminationMode {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => IlluminationMode::Light,
+            2 => IlluminationMode::Dark,
+            _ => IlluminationMode::Unknown,
+        }
+    }
+}
+
+impl From<IlluminationMode> for u8 {
+    fn from(value: IlluminationMode) -> Self {
+        match value {
+            IlluminationMode::Unknown => 0,
+            IlluminationMode::Light => 1,
+            IlluminationMode::Dark => 2,
+        }
+    }
+}
+
+/// A frame from one of the glasses' cameras, copied out of the service's buffer
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq)]
+pub struct CameraImage {
+    pub camera: GlassesCamera,
+    pub illumination: IlluminationMode,
+    pub width: u32,
+    pub height: u32,
+    /// One byte per pixel, a row at a time with no padding between rows
+    pub pixels: Vec<u8>,
+    /// Where the camera was when the frame was taken, in gameboard space like
+    /// the SDK reports it (`posCAM_GBD`)
+    pub position: Vec3,
+    /// The rotation from gameboard space to the camera (`rotToCAM_GBD`)
+    pub rotation: Quat,
+}
+
+impl CameraImage {
+    /// The brightness of the pixel at `x`, `y`
+    pub fn pixel(&self, x: u32, y: u32) -> u8 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+/// The buffers submitted to the service for a pair of glasses, by the address
+/// of their pixels, and the cameras they're for
+#[derive(Default)]
+pub(super) struct CameraBuffers {
+    buffers: HashMap<usize, (GlassesCamera, Box<[u8]>)>,
+    streaming: HashSet<GlassesCamera>,
+}
+
+impl T5Client {
+    /// Start or stop streaming from one of the glasses' cameras.
+    ///
+    /// Starting a stream hands the service `settings.camera_buffers` buffers
+    /// to fill, and stopping it takes them back.
+    pub fn configure_camera_stream(
+        &mut self,
+        id: &Glasses,
+        camera: GlassesCamera,
+        enabled: bool,
+    ) -> T5Result<()> {
+        let glasses = *self
+            .glasses
+            .get(id)
+            .ok_or_else(|| T5Error::GlassesNotFound(id.to_string()))?;
+        let config = T5_CameraStreamConfig {
+            cameraIndex: camera.into(),
+            enabled,
+        };
+        unsafe {
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5ConfigureCameraStreamForGlasses(glasses, config)
+            })?;
+        }
+
+        let buffers = self.camera_buffers.entry(id.clone()).or_default();
+        if enabled {
+            buffers.streaming.insert(camera);
+            unsafe {
+                top_up(
+                    &self.bridge,
+                    glasses,
+                    buffers,
+                    camera,
+                    self.settings.camera_buffers,
+                )?
+            };
+        } else {
+            buffers.streaming.remove(&camera);
+            let cancelled: Vec<usize> = buffers
+                .buffers
+                .iter()
+                .filter(|(_, (c, _))| *c == camera)
+                .map(|(address, _)| *address)
+                .collect();
+            for address in cancelled {
+                unsafe { cancel_buffer(&self.bridge, glasses, buffers, address)? };
+            }
+        }
+        Ok(())
+    }
+
+    /// Drain the frames the service has filled for the glasses, handing the
+    /// buffers straight back to be filled again.
+    ///
+    /// Buffers the service wouldn't take back are replaced on the next call,
+    /// so each stream keeps `settings.camera_buffers` of them.
+    pub fn get_camera_images(&mut self, id: &Glasses) -> T5Result<Vec<CameraImage>> {
+        let Some(buffers) = self.camera_buffers.get_mut(id) else {
+            return Ok(vec![]);
+        };
+        let glasses = *self
+            .glasses
+            .get(id)
+            .ok_or_else(|| T5Error::GlassesNotFound(id.to_string()))?;
+
+        let streaming: Vec<GlassesCamera> = buffers.streaming.iter().copied().collect();
+        for camera in streaming {
+            let count = self.settings.camera_buffers;
+            if let Err(err) = unsafe { top_up(&self.bridge, glasses, buffers, camera, count) } {
+                warn!("Couldn't replace the {camera:?} camera buffers of glasses {id}: {err}");
+            }
+        }
+
+        let mut images = vec![];
+        while !buffers.buffers.is_empty() {
+            let mut image = empty_image(GlassesCamera::Tangible, &mut []);
+            let result = unsafe {
+                op::<_, 1>(|| self.bridge.t5GetFilledCamImageBuffer(glasses, &mut image))
+            };
+            match result {
+                Ok(()) => {}
+                Err(T5Error::TryAgain) => break,
+                Err(err) => return Err(err),
+            }
+            let Some((camera, buffer)) = buffers.buffers.remove(&(image.pixelData as usize)) else {
+                continue;
+            };
+
+            let width = image.imageWidth as usize;
+            let stride = image.imageStride as usize;
+            let mut pixels = Vec::with_capacity(width * image.imageHeight as usize);
+            for row in buffer
+                .chunks(stride.max(1))
+                .take(image.imageHeight as usize)
+            {
+                pixels.extend_from_slice(&row[..width.min(row.len())]);
+            }
+            if let Ok(camera) = GlassesCamera::try_from(image.cameraIndex) {
+                images.push(CameraImage {
+                    camera,
+                    illumination: image.illuminationMode.into(),
+                    width: image.imageWidth as u32,
+                    height: image.imageHeight as u32,
+                    pixels,
+                    position: image.posCAM_GBD.into(),
+                    rotation: image.rotToCAM_GBD.into(),
+                });
+            }
+
+            // A buffer that can't be handed back is replaced on the next call
+            if let Err(err) =
+                unsafe { submit_buffer(&self.bridge, glasses, buffers, camera, buffer) }
+            {
+                warn!("Couldn't hand a {camera:?} camera buffer back to glasses {id}: {err}");
+            }
+        }
+        Ok(images)
+    }
+
+    /// Take back every buffer handed to the service for the glasses
+    pub(super) fn cancel_camera_buffers(&mut self, id: &Glasses) {
+        let Some(mut buffers) = self.camera_buffers.remove(id) else {
+            return;
+        };
+        let Some(glasses) = self.glasses.get(id) else {
+            return;
+        };
+        let addresses: Vec<usize> = buffers.buffers.keys().copied().collect();
+        for address in addresses {
+            let _ = unsafe { cancel_buffer(&self.bridge, *glasses, &mut buffers, address) };
+        }
+        // The service might still write to buffers it wouldn't give back
+        for (_, (_, buffer)) in buffers.buffers.drain() {
+            Box::leak(buffer);
+        }
+    }
+}
+
+fn empty_image(camera: GlassesCamera, buffer: &mut [u8]) -> T5_CamImage {
+    T5_CamImage {
+        imageWidth: 0,
+        imageHeight: 0,
+        imageStride: 0,
+        cameraIndex: camera.into(),
+        illuminationMode: 0,
+        bufferSize: buffer.len() as u32,
+        pixelData: buffer.as_mut_ptr(),
+        posCAM_GBD: Vec3::ZERO.into(),
+        rotToCAM_GBD: Quat::IDENTITY.into(),
+    }
+}
+
+/// Submit new buffers for the camera until it has `count` of them
+///
+/// # Safety
+/// `glasses` has to be a live handle
+unsafe fn top_up(
+    bridge: &TiltFiveNative,
+    glasses: T5_Glasses,
+    buffers: &mut CameraBuffers,
+    camera: GlassesCamera,
+    count: usize,
+) -> T5Result<()> {
+    let submitted = buffers
+        .buffers
+        .values()
+        .filter(|(c, _)| *c == camera)
+        .count();
+    for _ in submitted..count {
+        let buffer = vec![0; CAMERA_BUFFER_SIZE].into_boxed_slice();
+        submit_buffer(bridge, glasses, buffers, camera, buffer)?;
+    }
+    Ok(())
+}
+
+/// # Safety
+/// `glasses` has to be a live handle - the buffer is kept in `buffers` until
+/// the service gives it back
+unsafe fn submit_buffer(
+    bridge: &TiltFiveNative,
+    glasses: T5_Glasses,
+    buffers: &mut CameraBuffers,
+    camera: GlassesCamera,
+    mut buffer: Box<[u8]>,
+) -> T5Result<()> {
+    let mut image = empty_image(camera, &mut buffer);
+    op::<_, 1>(|| bridge.t5SubmitEmptyCamImageBuffer(glasses, &mut image))?;
+    buffers
+        .buffers
+        .insert(image.pixelData as usize, (camera, buffer));
+    Ok(())
+}
+
+/// # Safety
+/// `glasses` has to be a live handle
+unsafe fn cancel_buffer(
+    bridge: &TiltFiveNative,
+    glasses: T5_Glasses,
+    buffers: &mut CameraBuffers,
+    address: usize,
+) -> T5Result<()> {
+    op::<_, 1>(|| bridge.t5CancelCamImageBuffer(glasses, address as *mut u8))?;
+    // Only freed once the service has let go of it
+    buffers.buffers.remove(&address);
+    Ok(())
+}
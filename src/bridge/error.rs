@@ -0,0 +1,269 @@
+use std::fmt::Display;
+
+use bevy::reflect::{FromReflect, Reflect};
+
+use super::ffi::*;
+
+pub type T5Result<T> = std::result::Result<T, T5Error>;
+
+/// Errors produced while talking to the Tilt Five service.
+///
+/// Every code from `t5-sdk/include/errors.h` has its own variant, and failures
+/// that happen on our side of the FFI boundary get their own variants too.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum T5Error {
+    Timeout,
+    NoContext,
+    NoLibrary,
+    Internal,
+    NoService,
+    IoFailure,
+    RequestIdUnknown,
+    InvalidArgs,
+    DeviceLost,
+    TargetNotFound,
+    InvalidState,
+    SettingUnknown,
+    SettingWrongType,
+    MiscRemote,
+    Overflow,
+    GraphicsApiUnavailable,
+    Unsupported,
+    DecodeError,
+    InvalidGfxContext,
+    GfxContextInitFail,
+    TryAgain,
+    Unavailable,
+    AlreadyConnected,
+    NotConnected,
+    StringOverflow,
+    ServiceIncompatible,
+    PermissionDenied,
+    InvalidBufferSize,
+    /// A code the SDK returned that isn't listed in `errors.h`
+    Unknown(u32),
+    /// The native library couldn't be loaded
+    LibraryLoad(String),
+    /// The native library wasn't at any of these paths
+    LibraryNotFound(Vec<String>),
+    /// The glasses aren't known to this client
+    GlassesNotFound(String),
+    /// A string couldn't be passed to or read from the SDK
+    InvalidString,
+}
+
+impl T5Error {
+    pub fn from_code(code: u32) -> Option<Self> {
+        Some(match code {
+            T5_SUCCESS => return None,
+            T5_TIMEOUT => T5Error::Timeout,
+            T5_ERROR_NO_CONTEXT => T5Error::NoContext,
+            T5_ERROR_NO_LIBRARY => T5Error::NoLibrary,
+            T5_ERROR_INTERNAL => T5Error::Internal,
+            T5_ERROR_NO_SERVICE => T5Error::NoService,
+            T5_ERROR_IO_FAILURE => T5Error::IoFailure,
+            T5_ERROR_REQUEST_ID_UNKNOWN => T5Error::RequestIdUnknown,
+            T5_ERROR_INVALID_ARGS => T5Error::InvalidArgs,
+            T5_ERROR_DEVICE_LOST => T5Error::DeviceLost,
+            T5_ERROR_TARGET_NOT_FOUND => T5Error::TargetNotFound,
+            T5_ERROR_INVALID_STATE => T5Error::InvalidState,
+            T5_ERROR_SETTING_UNKNOWN => T5Error::SettingUnknown,
+            T5_ERROR_SETTING_WRONG_TYPE => T5Error::SettingWrongType,
+            T5_ERROR_MISC_REMOTE => T5Error::MiscRemote,
+            T5_ERROR_OVERFLOW => T5Error::Overflow,
+            T5_ERROR_GRAPHICS_API_UNAVAILABLE => T5Error::GraphicsApiUnavailable,
+            T5_ERROR_UNSUPPORTED => T5Error::Unsupported,
+            T5_ERROR_DECODE_ERROR => T5Error::DecodeError,
+            T5_ERROR_INVALID_GFX_CONTEXT => T5Error::InvalidGfxContext,
+            T5_ERROR_GFX_CONTEXT_INIT_FAIL => T5Error::GfxContextInitFail,
+            T5_ERROR_TRY_AGAIN => T5Error::TryAgain,
+            T5_ERROR_UNAVAILABLE => T5Error::Unavailable,
+            T5_ERROR_ALREADY_CONNECTED => T5Error::AlreadyConnected,
+            T5_ERROR_NOT_CONNECTED => T5Error::NotConnected,
+            T5_ERROR_STRING_OVERFLOW => T5Error::StringOverflow,
+            T5_ERROR_SERVICE_INCOMPATIBLE => T5Error::ServiceIncompatible,
+            T5_PERMISSION_DENIED => T5Error::PermissionDenied,
+            T5_ERROR_INVALID_BUFFER_SIZE => T5Error::InvalidBufferSize,
+            code => T5Error::Unknown(code),
+        })
+    }
+
+    /// The raw SDK code, if this error came from the SDK
+    pub fn code(&self) -> Option<u32> {
+        Some(match self {
+            T5Error::Timeout => T5_TIMEOUT,
+            T5Error::NoContext => T5_ERROR_NO_CONTEXT,
+            T5Error::NoLibrary => T5_ERROR_NO_LIBRARY,
+            T5Error::Internal => T5_ERROR_INTERNAL,
+            T5Error::NoService => T5_ERROR_NO_SERVICE,
+            T5Error::IoFailure => T5_ERROR_IO_FAILURE,
+            T5Error::RequestIdUnknown => T5_ERROR_REQUEST_ID_UNKNOWN,
+            T5Error::InvalidArgs => T5_ERROR_INVALID_ARGS,
+            T5Error::DeviceLost => T5_ERROR_DEVICE_LOST,
+            T5Error::TargetNotFound => T5_ERROR_TARGET_NOT_FOUND,
+            T5Error::InvalidState => T5_ERROR_INVALID_STATE,
+            T5Error::SettingUnknown => T5_ERROR_SETTING_UNKNOWN,
+            T5Error::SettingWrongType => T5_ERROR_SETTING_WRONG_TYPE,
+            T5Error::MiscRemote => T5_ERROR_MISC_REMOTE,
+            T5Error::Overflow => T5_ERROR_OVERFLOW,
+            T5Error::GraphicsApiUnavailable => T5_ERROR_GRAPHICS_API_UNAVAILABLE,
+            T5Error::Unsupported => T5_ERROR_UNSUPPORTED,
+            T5Error::DecodeError => T5_ERROR_DECODE_ERROR,
+            T5Error::InvalidGfxContext => T5_ERROR_INVALID_GFX_CONTEXT,
+            T5Error::GfxContextInitFail => T5_ERROR_GFX_CONTEXT_INIT_FAIL,
+            T5Error::TryAgain => T5_ERROR_TRY_AGAIN,
+            T5Error::Unavailable => T5_ERROR_UNAVAILABLE,
+            T5Error::AlreadyConnected => T5_ERROR_ALREADY_CONNECTED,
+            T5Error::NotConnected => T5_ERROR_NOT_CONNECTED,
+            T5Error::StringOverflow => T5_ERROR_STRING_OVERFLOW,
+            T5Error::ServiceIncompatible => T5_ERROR_SERVICE_INCOMPATIBLE,
+            T5Error::PermissionDenied => T5_PERMISSION_DENIED,
+            T5Error::InvalidBufferSize => T5_ERROR_INVALID_BUFFER_SIZE,
+            T5Error::Unknown(code) => *code,
+            T5Error::LibraryLoad(_)
+            | T5Error::LibraryNotFound(_)
+            | T5Error::GlassesNotFound(_)
+            | T5Error::InvalidString => return None,
+        })
+    }
+
+    /// Errors that are expected to clear up on their own, so the same call
+    /// is worth making again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            T5Error::Timeout
+                | T5Error::NoService
+                | T5Error::IoFailure
+                | T5Error::TryAgain
+                | T5Error::Unavailable
+        )
+    }
+
+    /// Errors that won't go away without user intervention - a missing or
+    /// incompatible service, a broken graphics setup and the like.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            T5Error::NoLibrary
+                | T5Error::LibraryLoad(_)
+                | T5Error::LibraryNotFound(_)
+                | T5Error::ServiceIncompatible
+                | T5Error::PermissionDenied
+                | T5Error::GraphicsApiUnavailable
+                | T5Error::InvalidGfxContext
+                | T5Error::GfxContextInitFail
+                | T5Error::Unsupported
+        )
+    }
+
+    /// Errors meaning the glasses went away and need to be connected again.
+    pub fn is_device_lost(&self) -> bool {
+        matches!(self, T5Error::DeviceLost | T5Error::NotConnected)
+    }
+}
+
+impl Display for T5Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            T5Error::Timeout => write!(f, "Timeout"),
+            T5Error::NoContext => write!(f, "No context"),
+            T5Error::NoLibrary => write!(f, "No library loaded"),
+            T5Error::Internal => write!(f, "An internal error occurred"),
+            T5Error::NoService => write!(f, "Service isn't connected"),
+            T5Error::IoFailure => write!(f, "Misc IO failure"),
+            T5Error::RequestIdUnknown => write!(f, "Service doesn't understand the request"),
+            T5Error::InvalidArgs => write!(f, "Argument(s) are invalid"),
+            T5Error::DeviceLost => write!(f, "Device lost"),
+            T5Error::TargetNotFound => write!(f, "Target (wand) not found"),
+            T5Error::InvalidState => write!(f, "Incorrect state for the request"),
+            T5Error::SettingUnknown => write!(f, "The requested param is unknown"),
+            T5Error::SettingWrongType => write!(
+                f,
+                "The requested param has a different type to the requested type"
+            ),
+            T5Error::MiscRemote => write!(f, "Miscellaneous remote error"),
+            T5Error::Overflow => write!(f, "Buffer overflow"),
+            T5Error::GraphicsApiUnavailable => write!(f, "Specified graphics API is unavailable"),
+            T5Error::Unsupported => write!(f, "Action is unsupported"),
+            T5Error::DecodeError => write!(f, "Failed to decode"),
+            T5Error::InvalidGfxContext => write!(f, "Graphics context is invalid"),
+            T5Error::GfxContextInitFail => write!(f, "Failed to initialize graphics context"),
+            T5Error::TryAgain => write!(f, "Target is not currently available"),
+            T5Error::Unavailable => write!(f, "Target is unavailable"),
+            T5Error::AlreadyConnected => write!(f, "The target is already connected"),
+            T5Error::NotConnected => write!(f, "The target is not connected"),
+            T5Error::StringOverflow => write!(f, "Overflow during string conversion operation"),
+            T5Error::ServiceIncompatible => write!(f, "Service incompatible"),
+            T5Error::PermissionDenied => write!(f, "Permission denied"),
+            T5Error::InvalidBufferSize => write!(f, "Invalid Buffer Size"),
+            T5Error::Unknown(code) => write!(f, "Unknown T5 error code {code:#x}"),
+            T5Error::LibraryLoad(reason) => {
+                write!(f, "Couldn't load the Tilt Five native library: {reason}")
+            }
+            T5Error::LibraryNotFound(tried) => write!(
+                f,
+                "Couldn't find the Tilt Five native library, set {} to its path. Tried: {}",
+                super::LIBRARY_PATH_VAR,
+                tried.join(", ")
+            ),
+            T5Error::GlassesNotFound(id) => write!(f, "Couldn't find glasses {id}"),
+            T5Error::InvalidString => write!(f, "Invalid string passed to or from the SDK"),
+        }
+    }
+}
+
+impl std::error::Error for T5Error {}
+
+impl From<std::ffi::NulError> for T5Error {
+    fn from(_: std::ffi::NulError) -> Self {
+        T5Error::InvalidString
+    }
+}
+
+impl From<std::str::Utf8Error> for T5Error {
+    fn from(_: std::str::Utf8Error) -> Self {
+        T5Error::InvalidString
+    }
+}
+
+impl From<libloading::Error> for T5Error {
+    fn from(value: libloading::Error) -> Self {
+        T5Error::LibraryLoad(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::T5Error;
+    use crate::bridge::ffi::*;
+
+    #[test]
+    fn error_codes_round_trip() {
+        for code in [
+            T5_TIMEOUT,
+            T5_ERROR_DEVICE_LOST,
+            T5_ERROR_TRY_AGAIN,
+            T5_ERROR_UNAVAILABLE,
+            T5_ERROR_ALREADY_CONNECTED,
+            T5_ERROR_SERVICE_INCOMPATIBLE,
+            T5_ERROR_INVALID_BUFFER_SIZE,
+            0x1013,
+        ] {
+            let err = T5Error::from_code(code).unwrap();
+            assert_eq!(err.code(), Some(code));
+        }
+        assert_eq!(T5Error::from_code(T5_SUCCESS), None);
+        assert_eq!(T5Error::from_code(0x1013), Some(T5Error::Unknown(0x1013)));
+    }
+
+    #[test]
+    fn retryable_and_fatal_are_disjoint() {
+        assert!(T5Error::TryAgain.is_retryable());
+        assert!(!T5Error::TryAgain.is_fatal());
+        assert!(T5Error::ServiceIncompatible.is_fatal());
+        assert!(!T5Error::ServiceIncompatible.is_retryable());
+        assert!(T5Error::DeviceLost.is_device_lost());
+        assert!(!T5Error::DeviceLost.is_fatal());
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use super::{ffi::TiltFiveNative, T5Error, T5Result};
+
+/// Set this to the path of the native library to load it from somewhere specific
+pub const LIBRARY_PATH_VAR: &str = "TILT_FIVE_NATIVE_LIBRARY";
+
+/// `TiltFiveNative.dll`, `libTiltFiveNative.so` and so on, depending on the target
+pub fn library_name() -> OsString {
+    libloading::library_filename("TiltFiveNative")
+}
+
+/// Where the SDK keeps the library for this target, within `t5-sdk/lib`
+fn bundled_directory() -> Option<&'static str> {
+    if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("win/x86_64")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("linux/x86_64")
+    } else if cfg!(all(target_os = "android", target_arch = "aarch64")) {
+        Some("android/arm64-v8a")
+    } else if cfg!(all(target_os = "android", target_arch = "arm")) {
+        Some("android/armeabi-v7a")
+    } else {
+        None
+    }
+}
+
+/// The paths to try loading the native library from, in order.
+///
+/// An explicit `path`, or one from `TILT_FIVE_NATIVE_LIBRARY`, is the only
+/// candidate when it's set. Otherwise the library is looked for next to the
+/// executable, then in the bundled `t5-sdk/lib` directory, and finally by
+/// name alone - which leaves it to the OS's own search paths.
+pub fn library_candidates(path: Option<&Path>) -> Vec<PathBuf> {
+    if let Some(path) = path {
+        return vec![path.to_path_buf()];
+    }
+    if let Some(path) = env::var_os(LIBRARY_PATH_VAR) {
+        return vec![path.into()];
+    }
+
+    let name = library_name();
+    let mut candidates = vec![];
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(dir.join(&name));
+    }
+    if let Some(bundled) = bundled_directory() {
+        let sdk_lib = Path::new("t5-sdk").join("lib").join(bundled).join(&name);
+        if let Ok(dir) = env::current_dir() {
+            candidates.push(dir.join(&sdk_lib));
+        }
+        let crate_sdk_lib = Path::new(env!("CARGO_MANIFEST_DIR")).join(&sdk_lib);
+        if !candidates.contains(&crate_sdk_lib) {
+            candidates.push(crate_sdk_lib);
+        }
+    }
+    candidates.push(name.into());
+    candidates
+}
+
+/// Load the native library from the first of `library_candidates` that exists
+pub fn load_library(path: Option<&Path>) -> T5Result<TiltFiveNative> {
+    let candidates = library_candidates(path);
+    for candidate in candidates.iter() {
+        // A bare file name is up to the OS to find, so there's nothing to check
+        let is_bare_name = candidate.parent() == Some(Path::new(""));
+        if !is_bare_name && !candidate.is_file() {
+            continue;
+        }
+        match unsafe { TiltFiveNative::new(candidate) } {
+            Ok(library) => return Ok(library),
+            Err(_) if is_bare_name => continue,
+            Err(err) => {
+                return Err(T5Error::LibraryLoad(format!(
+                    "{}: {err}",
+                    candidate.display()
+                )))
+            }
+        }
+    }
+    Err(T5Error::LibraryNotFound(
+        candidates
+            .iter()
+            .map(|candidate| candidate.display().to_string())
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{library_candidates, library_name, load_library};
+    use crate::bridge::T5Error;
+
+    #[test]
+    fn candidates_end_with_the_os_search() {
+        let candidates = library_candidates(None);
+        let exe = std::env::current_exe().unwrap();
+        if std::env::var_os(super::LIBRARY_PATH_VAR).is_none() {
+            assert_eq!(candidates[0], exe.parent().unwrap().join(library_name()));
+            assert_eq!(candidates.last().unwrap(), Path::new(&library_name()));
+        }
+    }
+
+    #[test]
+    fn missing_library_lists_the_paths_tried() {
+        let path = Path::new("does-not-exist").join(library_name());
+        match load_library(Some(&path)) {
+            Err(T5Error::LibraryNotFound(tried)) => {
+                assert_eq!(tried, vec![path.display().to_string()]);
+                let message = T5Error::LibraryNotFound(tried).to_string();
+                assert!(message.contains(&path.display().to_string()));
+            }
+            Err(err) => panic!("unexpected error {err}"),
+            Ok(_) => panic!("a missing library shouldn't load"),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-pub mod ffi {
+pub(crate) mod ffi {
     #![allow(non_upper_case_globals)]
     #![allow(non_camel_case_types)]
     #![allow(non_snake_case)]
@@ -6,28 +6,44 @@ pub mod ffi {
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
+mod camera;
+mod error;
+mod library;
+
 use std::{
     collections::HashMap,
     ffi::{c_char, c_void, CStr, CString},
     fmt::Display,
     mem::MaybeUninit,
+    path::PathBuf,
     thread,
     time::Duration,
 };
 
 use bevy::{
-    prelude::{Quat, Vec2, Vec3},
+    prelude::{Component, Quat, Vec2, Vec3},
     reflect::{FromReflect, Reflect},
 };
 use ffi::*;
 
-use anyhow::{bail, Result};
+use crate::settings::{GlassesPoseUsage, RetryPolicy, TiltFiveSettings};
+
+pub use camera::{CameraImage, GlassesCamera, IlluminationMode};
+pub use error::{T5Error, T5Result};
+pub use library::{library_candidates, library_name, LIBRARY_PATH_VAR};
 
 pub struct T5Client {
-    app: String,
+    settings: TiltFiveSettings,
     bridge: TiltFiveNative,
     ctx: T5_Context,
     glasses: HashMap<Glasses, T5_Glasses>,
+    /// Glasses part way through connecting, and the step they're waiting on
+    connecting: HashMap<Glasses, (T5_Glasses, ConnectionProgress)>,
+    /// Display names set for particular glasses, used instead of the one
+    /// from the settings template - kept for when the glasses reconnect
+    display_names: HashMap<Glasses, String>,
+    /// Image buffers handed to the service for each pair of glasses' cameras
+    camera_buffers: HashMap<Glasses, camera::CameraBuffers>,
     graphics_context: Option<(T5_GraphicsApi, *mut c_void)>,
 }
 
@@ -74,7 +90,18 @@ pub const DEFAULT_GLASSES_WIDTH: u32 = 1216;
 pub const DEFAULT_GLASSES_HEIGHT: u32 = 768;
 pub const DEFAULT_GLASSES_FOV: f32 = 48.0;
 
-fn op<T: FnMut() -> u32, const N: usize>(mut f: T) -> Result<()> {
+fn op<T: FnMut() -> u32, const N: usize>(f: T) -> T5Result<()> {
+    retry(
+        &RetryPolicy {
+            attempts: N as u32,
+            delay: Duration::from_millis(10),
+        },
+        f,
+    )
+}
+
+/// Call `f`, trying again according to `policy` while the service isn't available
+fn retry<T: FnMut() -> u32>(policy: &RetryPolicy, mut f: T) -> T5Result<()> {
     #![allow(unused_assignments)]
     let mut err = u32::MAX;
     let mut attempts = 0;
@@ -82,28 +109,55 @@ fn op<T: FnMut() -> u32, const N: usize>(mut f: T) -> Result<()> {
         err = f();
         if err == 0 {
             return Ok(());
-        } else if err == T5_ERROR_NO_SERVICE && attempts < N {
-            thread::sleep(Duration::from_millis(10));
+        } else if err == T5_ERROR_NO_SERVICE && attempts < policy.attempts {
+            thread::sleep(policy.delay);
             attempts += 1;
             continue;
         }
         break;
     }
-    if err != 0 {
-        bail!("T5 Client Error: {err}");
-    } else {
-        Ok(())
+    match T5Error::from_code(err) {
+        Some(err) => Err(err),
+        None => Ok(()),
     }
 }
 
+/// Errors that leave a connection waiting on the same step, rather than
+/// abandoning it
+fn is_still_connecting(err: &T5Error) -> bool {
+    matches!(err, T5Error::TryAgain | T5Error::NoService)
+}
+
 impl T5Client {
-    pub fn new<T: Into<String>, R: Into<String>>(app: T, version: R) -> Result<T5Client> {
+    /// Create a client, looking for the native library in the places
+    /// `library_candidates` lists
+    pub fn new<T: Into<String>, R: Into<String>>(app: T, version: R) -> T5Result<T5Client> {
+        Self::with_settings(&TiltFiveSettings {
+            application_id: app.into(),
+            application_version: version.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Create a client using the native library at `library`
+    pub fn with_library<P: Into<PathBuf>, T: Into<String>, R: Into<String>>(
+        library: P,
+        app: T,
+        version: R,
+    ) -> T5Result<T5Client> {
+        Self::with_settings(&TiltFiveSettings {
+            application_id: app.into(),
+            application_version: version.into(),
+            library_path: Some(library.into()),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_settings(settings: &TiltFiveSettings) -> T5Result<T5Client> {
         unsafe {
-            let app: String = app.into();
-            let version: String = version.into();
-            let app_id = CString::new(app.clone())?;
-            let version = CString::new(version)?;
-            let bridge = TiltFiveNative::new("TiltFiveNative.dll")?;
+            let bridge = library::load_library(settings.library_path.as_deref())?;
+            let app_id = CString::new(settings.application_id.clone())?;
+            let version = CString::new(settings.application_version.clone())?;
             let mut ctx = MaybeUninit::uninit();
             let mut platform = MaybeUninit::uninit();
             let info = T5_ClientInfo {
@@ -112,34 +166,37 @@ impl T5Client {
                 sdkType: 0u8,
                 reserved: 0u64,
             };
-            op::<_, 100>(|| {
+            retry(&settings.retry_policy, || {
                 bridge.t5CreateContext(ctx.as_mut_ptr(), &info, platform.as_mut_ptr())
             })?;
 
             let ctx = ctx.assume_init();
 
             Ok(T5Client {
-                app,
+                settings: settings.clone(),
                 bridge,
                 ctx,
                 glasses: Default::default(),
+                connecting: Default::default(),
+                display_names: Default::default(),
+                camera_buffers: Default::default(),
                 graphics_context: None,
             })
         }
     }
 
-    #[allow(dead_code)]
+    /// The viewable extents of a kind of board, in meters from its center
     pub fn get_gameboard_size(
         &mut self,
         gameboard_type: T5GameboardType,
-    ) -> Result<T5_GameboardSize> {
+    ) -> T5Result<T5_GameboardSize> {
         unsafe {
             let mut gameboard = MaybeUninit::uninit();
 
             op::<_, 100>(|| {
                 self.bridge.t5GetGameboardSize(
                     self.ctx,
-                    gameboard_type as i32,
+                    gameboard_type.into(),
                     gameboard.as_mut_ptr(),
                 )
             })?;
@@ -148,7 +205,7 @@ impl T5Client {
         }
     }
 
-    pub fn list_glasses(&mut self) -> Result<Vec<Glasses>> {
+    pub fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
         unsafe {
             let mut tmp = vec![];
             let mut buffer = [c_char::MIN; 1024];
@@ -181,75 +238,230 @@ impl T5Client {
         }
     }
 
-    pub fn create_glasses(&mut self, glasses_id: &Glasses) -> Result<(Glasses, Option<String>)> {
-        unsafe {
-            let id: &str = glasses_id.into();
-            let id = CString::new(id)?;
-            let mut glasses = MaybeUninit::uninit();
-            op::<_, 100>(|| {
-                self.bridge
-                    .t5CreateGlasses(self.ctx, id.as_ptr(), glasses.as_mut_ptr())
-            })?;
-
-            let value = glasses.assume_init();
-
-            let app = &self.app;
+    /// Connect to the glasses, blocking until they're ready or the retry
+    /// policy runs out
+    pub fn create_glasses(&mut self, glasses_id: &Glasses) -> T5Result<(Glasses, Option<String>)> {
+        let policy = self.settings.retry_policy.clone();
+        let mut attempts = 0;
+        let mut last = None;
+        loop {
+            let progress = self.connect_glasses(glasses_id)?;
+            if progress == ConnectionProgress::Ready {
+                break;
+            }
+            // Only steps that have to wait count as attempts
+            if last.as_ref() == Some(&progress) {
+                if attempts >= policy.attempts {
+                    self.cancel_connection(glasses_id);
+                    return Err(T5Error::Timeout);
+                }
+                thread::sleep(policy.delay);
+                attempts += 1;
+            }
+            last = Some(progress);
+        }
+        let friendly_name = self.get_glasses_name(glasses_id).ok();
+        Ok((glasses_id.clone(), friendly_name))
+    }
 
-            let name = CString::new(format!("{app} - {glasses_id}"))?;
+    /// Take the next step towards connecting to the glasses, without waiting
+    /// on the service - like the SDK's `GlassesConnectionHelper`.
+    ///
+    /// Call this again until it returns `Ready`. The service not being up yet,
+    /// or the glasses asking to try again, leaves the connection where it is;
+    /// any other error abandons it.
+    pub fn connect_glasses(&mut self, glasses_id: &Glasses) -> T5Result<ConnectionProgress> {
+        if self.glasses.contains_key(glasses_id) {
+            return Ok(ConnectionProgress::Ready);
+        }
+        let (glasses, progress) = match self.connecting.get(glasses_id) {
+            Some(connecting) => connecting.clone(),
+            None => unsafe {
+                let id: &str = glasses_id.into();
+                let id = CString::new(id)?;
+                let mut glasses = MaybeUninit::uninit();
+                match op::<_, 1>(|| {
+                    self.bridge
+                        .t5CreateGlasses(self.ctx, id.as_ptr(), glasses.as_mut_ptr())
+                }) {
+                    Ok(()) => (glasses.assume_init(), ConnectionProgress::Reserving),
+                    Err(err) if is_still_connecting(&err) => {
+                        return Ok(ConnectionProgress::Reserving)
+                    }
+                    Err(err) => return Err(err),
+                }
+            },
+        };
 
-            op::<_, 100>(|| self.bridge.t5ReserveGlasses(value, name.as_ptr()))?;
-            op::<_, 100>(|| self.bridge.t5EnsureGlassesReady(value))?;
-            if let Some((api, ctx)) = &self.graphics_context {
-                op::<_, 100>(|| self.bridge.t5InitGlassesGraphicsContext(value, *api, *ctx))?;
+        match unsafe { self.connection_step(glasses_id, glasses, &progress) } {
+            Ok(ConnectionProgress::Ready) => {
+                self.connecting.remove(glasses_id);
+                self.glasses.insert(glasses_id.clone(), glasses);
+                Ok(ConnectionProgress::Ready)
+            }
+            Ok(progress) => {
+                self.connecting
+                    .insert(glasses_id.clone(), (glasses, progress.clone()));
+                Ok(progress)
+            }
+            Err(err) if is_still_connecting(&err) => {
+                self.connecting
+                    .insert(glasses_id.clone(), (glasses, progress.clone()));
+                Ok(progress)
             }
+            Err(err) => {
+                self.connecting
+                    .insert(glasses_id.clone(), (glasses, progress));
+                self.cancel_connection(glasses_id);
+                Err(err)
+            }
+        }
+    }
 
-            let config = T5_WandStreamConfig { enabled: true };
-            op::<_, 100>(|| self.bridge.t5ConfigureWandStreamForGlasses(value, &config))?;
+    /// Make a single attempt at the step the connection is waiting on,
+    /// returning the step it's waiting on after that
+    unsafe fn connection_step(
+        &self,
+        glasses_id: &Glasses,
+        glasses: T5_Glasses,
+        progress: &ConnectionProgress,
+    ) -> T5Result<ConnectionProgress> {
+        match progress {
+            ConnectionProgress::Reserving => {
+                let name = CString::new(self.display_name(glasses_id))?;
+                op::<_, 1>(|| self.bridge.t5ReserveGlasses(glasses, name.as_ptr()))?;
+                Ok(ConnectionProgress::WaitingForReady)
+            }
+            ConnectionProgress::WaitingForReady => {
+                op::<_, 1>(|| self.bridge.t5EnsureGlassesReady(glasses))?;
+                Ok(ConnectionProgress::InitializingGraphics)
+            }
+            // Each step after this only repeats itself when asked to try
+            // again - the service won't take a graphics context twice
+            ConnectionProgress::InitializingGraphics => {
+                if let Some((api, ctx)) = &self.graphics_context {
+                    op::<_, 1>(|| {
+                        self.bridge
+                            .t5InitGlassesGraphicsContext(glasses, *api, *ctx)
+                    })?;
+                }
+                Ok(ConnectionProgress::ConfiguringWands)
+            }
+            ConnectionProgress::ConfiguringWands => {
+                let config = T5_WandStreamConfig { enabled: true };
+                op::<_, 1>(|| {
+                    self.bridge
+                        .t5ConfigureWandStreamForGlasses(glasses, &config)
+                })?;
+                Ok(ConnectionProgress::Ready)
+            }
+            progress => Ok(progress.clone()),
+        }
+    }
 
-            let id: Glasses = glasses_id.clone();
-            self.glasses.insert(id.clone(), value);
+    /// The name the glasses show while they're reserved
+    pub fn display_name(&self, glasses_id: &Glasses) -> String {
+        self.display_names
+            .get(glasses_id)
+            .cloned()
+            .unwrap_or_else(|| self.settings.display_name(glasses_id))
+    }
 
-            let friendly_name = self.get_glasses_name(&id).ok();
+    /// Change the name the glasses show while they're reserved - right away if
+    /// they're connected, or once they are otherwise
+    pub fn set_glasses_display_name(&mut self, id: &Glasses, display_name: &str) -> T5Result<()> {
+        let name = CString::new(display_name)?;
+        self.display_names
+            .insert(id.clone(), display_name.to_string());
+        if let Some(glasses) = self.glasses.get(id) {
+            unsafe {
+                op::<_, 1>(|| self.bridge.t5SetGlassesDisplayName(*glasses, name.as_ptr()))?;
+            }
+        }
+        Ok(())
+    }
 
-            Ok((id, friendly_name))
+    /// Abandon a connection part way through, destroying its handle
+    fn cancel_connection(&mut self, glasses_id: &Glasses) {
+        if let Some((mut glasses, progress)) = self.connecting.remove(glasses_id) {
+            unsafe {
+                if progress != ConnectionProgress::Reserving {
+                    self.bridge.t5ReleaseGlasses(glasses);
+                }
+                self.bridge.t5DestroyGlasses(&mut glasses);
+            }
         }
     }
 
-    pub fn release_glasses(&mut self, glasses: Glasses) -> Result<()> {
-        if let Some(glasses) = self.glasses.remove(&glasses) {
+    /// Release the glasses and destroy their handle.
+    ///
+    /// The handle is destroyed even when the glasses can't be reached any
+    /// more, so lost glasses can be released before reconnecting to them.
+    pub fn release_glasses(&mut self, glasses: Glasses) -> T5Result<()> {
+        self.cancel_connection(&glasses);
+        self.cancel_camera_buffers(&glasses);
+        if let Some(mut glasses) = self.glasses.remove(&glasses) {
             unsafe {
                 let config = T5_WandStreamConfig { enabled: false };
-                op::<_, 100>(|| {
+                let _ = op::<_, 1>(|| {
                     self.bridge
                         .t5ConfigureWandStreamForGlasses(glasses, &config)
-                })?;
-                op::<_, 100>(|| self.bridge.t5ReleaseGlasses(glasses))
+                });
+                let result = op::<_, 100>(|| self.bridge.t5ReleaseGlasses(glasses));
+                self.bridge.t5DestroyGlasses(&mut glasses);
+                result
             }
         } else {
             Ok(())
         }
     }
 
-    pub fn get_glasses_pose(&mut self, glasses: &Glasses) -> Result<T5_GlassesPose> {
+    pub fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.get_pose(glasses, self.settings.pose_usage)
+    }
+
+    /// The pose the SDK smooths for showing what the player sees on another
+    /// display - the presentation pose is too jittery off the glasses
+    pub fn get_spectator_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.get_pose(glasses, GlassesPoseUsage::SpectatorPresentation)
+    }
+
+    fn get_pose(&mut self, glasses: &Glasses, usage: GlassesPoseUsage) -> T5Result<T5_GlassesPose> {
         if let Some(glasses) = self.glasses.get(glasses) {
             unsafe {
                 let mut pose = MaybeUninit::uninit();
                 op::<_, 1>(|| {
-                    self.bridge.t5GetGlassesPose(
-                        *glasses,
-                        T5_GlassesPoseUsage_kT5_GlassesPoseUsage_GlassesPresentation,
-                        pose.as_mut_ptr(),
-                    )
+                    self.bridge
+                        .t5GetGlassesPose(*glasses, usage.into(), pose.as_mut_ptr())
                 })?;
                 Ok(pose.assume_init())
             }
         } else {
-            bail!("Couldn't find glasses");
+            Err(T5Error::GlassesNotFound(glasses.to_string()))
         }
     }
 
-    #[allow(dead_code)]
-    pub fn get_wand_stream_events(&mut self, glasses: &Glasses) -> Result<Vec<T5_WandStreamEvent>> {
+    /// The wands currently connected to the glasses
+    pub fn list_wands(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandHandle>> {
+        let glasses = *self
+            .glasses
+            .get(glasses)
+            .ok_or_else(|| T5Error::GlassesNotFound(glasses.to_string()))?;
+        let mut buffer = [0; u8::MAX as usize];
+        let mut count = u8::MAX;
+        unsafe {
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5ListWandsForGlasses(glasses, buffer.as_mut_ptr(), &mut count)
+            })?;
+        }
+        Ok(buffer[..count as usize].to_vec())
+    }
+
+    pub fn get_wand_stream_events(
+        &mut self,
+        glasses: &Glasses,
+    ) -> T5Result<Vec<T5_WandStreamEvent>> {
         if let Some(glasses) = self.glasses.get(glasses) {
             unsafe {
                 let mut events = vec![];
@@ -271,7 +483,7 @@ impl T5Client {
                 Ok(events)
             }
         } else {
-            bail!("Couldn't find glasses");
+            Err(T5Error::GlassesNotFound(glasses.to_string()))
         }
     }
 
@@ -279,19 +491,55 @@ impl T5Client {
         &mut self,
         id: &Glasses,
         info: *const T5_FrameInfo,
-    ) -> Result<()> {
+    ) -> T5Result<()> {
         if let Some(glasses) = self.glasses.get(id) {
             op::<_, 1>(|| self.bridge.t5SendFrameToGlasses(*glasses, info))
         } else {
-            bail!("couldn't find glasses");
+            Err(T5Error::GlassesNotFound(id.to_string()))
+        }
+    }
+
+    /// Set the graphics context frames are sent from. Glasses already past
+    /// initializing their graphics get it right away, and ones still connecting
+    /// get it when they get there.
+    ///
+    /// # Safety
+    /// `device` must be a live `ID3D11Device`, kept alive for as long as
+    /// frames are sent from it.
+    pub unsafe fn set_dx11_graphics_context(&mut self, device: *mut c_void) -> T5Result<()> {
+        let api = T5_GraphicsApi_kT5_GraphicsApi_D3D11;
+        self.graphics_context = Some((api, device));
+        let initialized = self.glasses.values().chain(
+            self.connecting
+                .values()
+                .filter(|(_, progress)| *progress == ConnectionProgress::ConfiguringWands)
+                .map(|(glasses, _)| glasses),
+        );
+        for glasses in initialized {
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5InitGlassesGraphicsContext(*glasses, api, device)
+            })?;
         }
+        Ok(())
     }
 
-    pub fn set_dx11_graphics_context(&mut self, device: *mut c_void) {
-        self.graphics_context = Some((T5_GraphicsApi_kT5_GraphicsApi_D3D11, device));
+    pub fn get_connection_state(&mut self, id: &Glasses) -> T5Result<GlassesConnectionState> {
+        if let Some(glasses) = self.glasses.get(id) {
+            unsafe {
+                let mut state = MaybeUninit::uninit();
+                op::<_, 1>(|| {
+                    self.bridge
+                        .t5GetGlassesConnectionState(*glasses, state.as_mut_ptr())
+                })?;
+                Ok(state.assume_init().into())
+            }
+        } else {
+            Err(T5Error::GlassesNotFound(id.to_string()))
+        }
     }
 
-    pub fn get_ipd(&mut self, id: &Glasses) -> Result<f32> {
+    pub fn get_ipd(&mut self, id: &Glasses) -> T5Result<f32> {
         if let Some(glasses) = self.glasses.get(id) {
             unsafe {
                 let mut ipd = MaybeUninit::uninit();
@@ -307,11 +555,11 @@ impl T5Client {
                 Ok(ipd)
             }
         } else {
-            bail!("couldn't find glasses");
+            Err(T5Error::GlassesNotFound(id.to_string()))
         }
     }
 
-    pub fn get_glasses_name(&mut self, id: &Glasses) -> Result<String> {
+    pub fn get_glasses_name(&mut self, id: &Glasses) -> T5Result<String> {
         if let Some(glasses) = self.glasses.get(id) {
             unsafe {
                 let mut buffer = [c_char::MIN; 1024];
@@ -327,17 +575,99 @@ impl T5Client {
                 })?;
                 let ptr = buffer.as_ptr();
                 let buffer = CStr::from_ptr(ptr);
-                let value = buffer.to_str()?;
-                if !value.is_empty() {
-                    Ok(value.to_string())
-                } else {
-                    bail!("couldn't get name");
-                }
+                Ok(buffer.to_str()?.to_string())
+            }
+        } else {
+            Err(T5Error::GlassesNotFound(id.to_string()))
+        }
+    }
+    /// The glasses params that changed since this was last called for the
+    /// glasses - params this version of the plugin doesn't know about are left out
+    pub fn get_changed_glasses_params(&mut self, id: &Glasses) -> T5Result<Vec<GlassesParam>> {
+        if let Some(glasses) = self.glasses.get(id) {
+            unsafe {
+                let mut buffer: [T5_ParamGlasses; 32] = [0; 32];
+                let mut count = buffer.len() as u16;
+                op::<_, 1>(|| {
+                    self.bridge
+                        .t5GetChangedGlassesParams(*glasses, buffer.as_mut_ptr(), &mut count)
+                })?;
+                Ok(buffer[..count as usize]
+                    .iter()
+                    .filter_map(|param| GlassesParam::try_from(*param).ok())
+                    .collect())
             }
         } else {
-            bail!("couldn't find glasses");
+            Err(T5Error::GlassesNotFound(id.to_string()))
+        }
+    }
+
+    pub fn get_system_integer_param(&mut self, param: SystemParam) -> T5Result<i64> {
+        unsafe {
+            let mut value = MaybeUninit::uninit();
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5GetSystemIntegerParam(self.ctx, param.into(), value.as_mut_ptr())
+            })?;
+            Ok(value.assume_init())
+        }
+    }
+
+    pub fn get_system_float_param(&mut self, param: SystemParam) -> T5Result<f64> {
+        unsafe {
+            let mut value = MaybeUninit::uninit();
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5GetSystemFloatParam(self.ctx, param.into(), value.as_mut_ptr())
+            })?;
+            Ok(value.assume_init())
+        }
+    }
+
+    pub fn get_system_utf8_param(&mut self, param: SystemParam) -> T5Result<String> {
+        unsafe {
+            let mut buffer = [c_char::MIN; 1024];
+            let mut buffer_size = 1024;
+            op::<_, 1>(|| {
+                self.bridge.t5GetSystemUtf8Param(
+                    self.ctx,
+                    param.into(),
+                    buffer.as_mut_ptr(),
+                    &mut buffer_size,
+                )
+            })?;
+            let value = CStr::from_ptr(buffer.as_ptr()).to_str()?;
+            Ok(value.to_string())
         }
     }
+
+    /// The system parameters that changed since this was last called - params
+    /// this version of the plugin doesn't know about are left out
+    pub fn get_changed_system_params(&mut self) -> T5Result<Vec<SystemParam>> {
+        unsafe {
+            let mut buffer: [T5_ParamSys; 32] = [0; 32];
+            let mut count = buffer.len() as u16;
+            op::<_, 1>(|| {
+                self.bridge
+                    .t5GetChangedSystemParams(self.ctx, buffer.as_mut_ptr(), &mut count)
+            })?;
+            Ok(buffer[..count as usize]
+                .iter()
+                .filter_map(|param| SystemParam::try_from(*param).ok())
+                .collect())
+        }
+    }
+
+    pub fn get_service_version(&mut self) -> T5Result<String> {
+        self.get_system_utf8_param(SystemParam::ServiceVersion)
+    }
+
+    /// Whether the Tilt Five control panel needs the user's attention - for
+    /// a firmware update, for example
+    pub fn is_attention_required(&mut self) -> T5Result<bool> {
+        self.get_system_integer_param(SystemParam::AttentionRequired)
+            .map(|value| value != 0)
+    }
 }
 
 impl Drop for T5Client {
@@ -348,14 +678,171 @@ impl Drop for T5Client {
     }
 }
 
-#[derive(Clone, Copy)]
+/// The kinds of board the glasses can see
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum T5GameboardType {
+    /// No board in sight
+    #[default]
     None = 1,
     LE = 2,
     XE = 3,
+    /// An XE board with its raised section folded up
     XeRaised = 4,
 }
 
+impl From<T5GameboardType> for T5_GameboardType {
+    fn from(value: T5GameboardType) -> Self {
+        value as T5_GameboardType
+    }
+}
+
+#[allow(non_upper_case_globals)]
+impl TryFrom<T5_GameboardType> for T5GameboardType {
+    type Error = T5Error;
+
+    fn try_from(value: T5_GameboardType) -> Result<Self, Self::Error> {
+        match value {
+            T5_GameboardType_kT5_GameboardType_None => Ok(T5GameboardType::None),
+            T5_GameboardType_kT5_GameboardType_LE => Ok(T5GameboardType::LE),
+            T5_GameboardType_kT5_GameboardType_XE => Ok(T5GameboardType::XE),
+            T5_GameboardType_kT5_GameboardType_XE_Raised => Ok(T5GameboardType::XeRaised),
+            _ => Err(T5Error::InvalidArgs),
+        }
+    }
+}
+
+/// How the glasses are connected to this app, as the service reports it.
+///
+/// Connected glasses have this as a component on their entity.
+#[derive(Component, Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlassesConnectionState {
+    /// Reserved and ready for this app to render to
+    ExclusiveConnection,
+    /// Reserved for this app, but not ready to render to yet
+    ExclusiveReservation,
+    /// Plugged in, but not reserved by this app
+    NotExclusivelyConnected,
+    /// Unplugged, or otherwise lost
+    Disconnected,
+}
+
+#[allow(non_upper_case_globals)]
+impl From<T5_ConnectionState> for GlassesConnectionState {
+    fn from(value: T5_ConnectionState) -> Self {
+        match value {
+            T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection => {
+                GlassesConnectionState::ExclusiveConnection
+            }
+            T5_ConnectionState_kT5_ConnectionState_ExclusiveReservation => {
+                GlassesConnectionState::ExclusiveReservation
+            }
+            T5_ConnectionState_kT5_ConnectionState_NotExclusivelyConnected => {
+                GlassesConnectionState::NotExclusivelyConnected
+            }
+            _ => GlassesConnectionState::Disconnected,
+        }
+    }
+}
+
+impl From<GlassesConnectionState> for T5_ConnectionState {
+    fn from(value: GlassesConnectionState) -> Self {
+        match value {
+            GlassesConnectionState::ExclusiveConnection => {
+                T5_ConnectionState_kT5_ConnectionState_ExclusiveConnection
+            }
+            GlassesConnectionState::ExclusiveReservation => {
+                T5_ConnectionState_kT5_ConnectionState_ExclusiveReservation
+            }
+            GlassesConnectionState::NotExclusivelyConnected => {
+                T5_ConnectionState_kT5_ConnectionState_NotExclusivelyConnected
+            }
+            GlassesConnectionState::Disconnected => {
+                T5_ConnectionState_kT5_ConnectionState_Disconnected
+            }
+        }
+    }
+}
+
+/// How far along connecting to a pair of glasses is.
+///
+/// Backends report the step a connection is waiting on, and errors when it
+/// fails - `Failed` is only used by the plugin, to report those errors.
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionProgress {
+    /// Waiting to reserve the glasses for this app
+    Reserving,
+    /// Reserved, waiting for the glasses to be ready to render to
+    WaitingForReady,
+    /// Ready, handing the glasses the graphics context frames come from
+    InitializingGraphics,
+    /// Turning on the stream of wand events
+    ConfiguringWands,
+    Ready,
+    Failed(T5Error),
+}
+
+/// Parameters belonging to a pair of glasses
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlassesParam {
+    /// The distance between the user's pupils, in millimeters
+    Ipd,
+    /// The name the user gave the glasses in the Tilt Five control panel
+    FriendlyName,
+}
+
+impl From<GlassesParam> for T5_ParamGlasses {
+    fn from(value: GlassesParam) -> Self {
+        match value {
+            GlassesParam::Ipd => T5_ParamGlasses_kT5_ParamGlasses_Float_IPD,
+            GlassesParam::FriendlyName => T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName,
+        }
+    }
+}
+
+#[allow(non_upper_case_globals)]
+impl TryFrom<T5_ParamGlasses> for GlassesParam {
+    type Error = T5Error;
+
+    fn try_from(value: T5_ParamGlasses) -> Result<Self, Self::Error> {
+        match value {
+            T5_ParamGlasses_kT5_ParamGlasses_Float_IPD => Ok(GlassesParam::Ipd),
+            T5_ParamGlasses_kT5_ParamGlasses_UTF8_FriendlyName => Ok(GlassesParam::FriendlyName),
+            _ => Err(T5Error::SettingUnknown),
+        }
+    }
+}
+
+/// Service wide parameters, as opposed to ones belonging to a pair of glasses
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemParam {
+    /// The version of the Tilt Five service, as a string
+    ServiceVersion,
+    /// Non-zero while the Tilt Five control panel needs the user's attention
+    AttentionRequired,
+}
+
+impl From<SystemParam> for T5_ParamSys {
+    fn from(value: SystemParam) -> Self {
+        match value {
+            SystemParam::ServiceVersion => T5_ParamSys_kT5_ParamSys_UTF8_Service_Version,
+            SystemParam::AttentionRequired => T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired,
+        }
+    }
+}
+
+#[allow(non_upper_case_globals)]
+impl TryFrom<T5_ParamSys> for SystemParam {
+    type Error = T5Error;
+
+    fn try_from(value: T5_ParamSys) -> Result<Self, Self::Error> {
+        match value {
+            T5_ParamSys_kT5_ParamSys_UTF8_Service_Version => Ok(SystemParam::ServiceVersion),
+            T5_ParamSys_kT5_ParamSys_Integer_CPL_AttRequired => Ok(SystemParam::AttentionRequired),
+            _ => Err(T5Error::SettingUnknown),
+        }
+    }
+}
+
 impl From<T5_Vec2> for Vec2 {
     fn from(val: T5_Vec2) -> Self {
         Vec2::new(val.x, val.y)
@@ -403,20 +890,184 @@ impl From<Quat> for T5_Quat {
 
 #[cfg(test)]
 mod tests {
-    use crate::bridge::T5GameboardType;
+    use std::{
+        env,
+        ffi::{c_char, CStr, CString},
+        path::PathBuf,
+    };
+
+    use crate::{
+        bridge::{
+            ffi::*, ConnectionProgress, GlassesCamera, GlassesConnectionState, GlassesParam,
+            IlluminationMode, SystemParam, T5Error, T5GameboardType,
+        },
+        TiltFiveSettings,
+    };
+
+    use super::{Glasses, T5Client};
+
+    /// The library the tests run against - the fake library built alongside the
+    /// tests, unless `TILT_FIVE_TEST_LIBRARY` points somewhere else
+    fn library_path() -> PathBuf {
+        if let Ok(path) = env::var("TILT_FIVE_TEST_LIBRARY") {
+            return path.into();
+        }
+        let exe = env::current_exe().unwrap();
+        exe.parent()
+            .unwrap()
+            .join(libloading::library_filename("TiltFiveNative"))
+    }
+
+    fn client() -> T5Client {
+        T5Client::with_library(library_path(), "test", "1").unwrap()
+    }
+
+    /// Handle on the scripting functions of the fake library
+    struct FakeService(libloading::Library);
+
+    impl FakeService {
+        fn new() -> Self {
+            unsafe { Self(libloading::Library::new(library_path()).unwrap()) }
+        }
 
-    use super::T5Client;
+        fn add_glasses(&self, id: &str, friendly_name: &str) -> Glasses {
+            let id_str = CString::new(id).unwrap();
+            let name = CString::new(friendly_name).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, *const c_char) -> u32>(
+                        b"fakeT5AddGlasses\0",
+                    )
+                    .unwrap();
+                assert_eq!(f(id_str.as_ptr(), name.as_ptr()), 0);
+            }
+            id.into()
+        }
+
+        fn display_name(&self, id: &str) -> String {
+            let id = CString::new(id).unwrap();
+            let mut buffer = [0u8; 256];
+            let mut size = buffer.len();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, *mut c_char, *mut usize) -> u32>(
+                        b"fakeT5GetGlassesDisplayName\0",
+                    )
+                    .unwrap();
+                assert_eq!(
+                    f(id.as_ptr(), buffer.as_mut_ptr() as *mut c_char, &mut size),
+                    0
+                );
+            }
+            CStr::from_bytes_until_nul(&buffer)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        }
+
+        fn set_pose(&self, id: &str, pose: T5_GlassesPose) {
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, *const T5_GlassesPose) -> u32>(
+                        b"fakeT5SetGlassesPose\0",
+                    )
+                    .unwrap();
+                assert_eq!(f(id.as_ptr(), &pose), 0);
+            }
+        }
+
+        fn set_connection_state(&self, id: &str, state: GlassesConnectionState) {
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, T5_ConnectionState) -> u32>(
+                        b"fakeT5SetGlassesConnectionState\0",
+                    )
+                    .unwrap();
+                assert_eq!(f(id.as_ptr(), state.into()), 0);
+            }
+        }
+
+        fn connect_wand(&self, id: &str, wand: u8) {
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, u8) -> u32>(b"fakeT5ConnectWand\0")
+                    .unwrap();
+                assert_eq!(f(id.as_ptr(), wand), 0);
+            }
+        }
+
+        fn set_ipd(&self, id: &str, ipd: f64) {
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, f64) -> u32>(
+                        b"fakeT5SetGlassesIpd\0",
+                    )
+                    .unwrap();
+                assert_eq!(f(id.as_ptr(), ipd), 0);
+            }
+        }
+
+        fn set_attention_required(&self, required: bool) {
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(i64) -> u32>(b"fakeT5SetAttentionRequired\0")
+                    .unwrap();
+                assert_eq!(f(required as i64), 0);
+            }
+        }
+
+        fn push_cam_image(&self, id: &str, image: T5_CamImage, pixels: &[u8]) {
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f =
+                    self.0
+                        .get::<unsafe extern "C" fn(
+                            *const c_char,
+                            *const T5_CamImage,
+                            *const u8,
+                            usize,
+                        ) -> u32>(b"fakeT5PushCamImage\0")
+                        .unwrap();
+                assert_eq!(f(id.as_ptr(), &image, pixels.as_ptr(), pixels.len()), 0);
+            }
+        }
+
+        fn inject_error(&self, id: &str, function: &str, error: u32, count: u32) {
+            let function = CString::new(function).unwrap();
+            let id = CString::new(id).unwrap();
+            unsafe {
+                let f = self
+                    .0
+                    .get::<unsafe extern "C" fn(*const c_char, *const c_char, u32, u32) -> u32>(
+                        b"fakeT5InjectError\0",
+                    )
+                    .unwrap();
+                assert_eq!(f(function.as_ptr(), id.as_ptr(), error, count), 0);
+            }
+        }
+    }
 
     #[test]
     fn can_create_context() {
-        let client = T5Client::new("test", "1");
+        let client = T5Client::with_library(library_path(), "test", "1");
 
         assert!(client.is_ok())
     }
 
     #[test]
     fn can_get_gameboard_size() {
-        let mut client = T5Client::new("test", "1").unwrap();
+        let mut client = client();
         let val = client.get_gameboard_size(T5GameboardType::LE).unwrap();
         assert_eq!(val.viewableExtentNegativeX, 0.35);
         assert_eq!(val.viewableExtentPositiveX, 0.35);
@@ -427,9 +1078,383 @@ mod tests {
 
     #[test]
     fn can_get_glasses() {
-        let mut client = T5Client::new("test", "1").unwrap();
+        let mut client = client();
         let glasses = client.list_glasses();
         println!("Glasses: {glasses:?}");
         assert!(glasses.is_ok());
     }
+
+    #[test]
+    fn can_connect_to_scripted_glasses() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-connect", "Scripted");
+        let mut client = client();
+
+        assert!(client.list_glasses().unwrap().contains(&id));
+        let (_, friendly_name) = client.create_glasses(&id).unwrap();
+        assert_eq!(friendly_name.as_deref(), Some("Scripted"));
+        assert_eq!(client.get_ipd(&id).unwrap(), 59.);
+
+        assert!(matches!(
+            client.get_glasses_pose(&id),
+            Err(T5Error::TryAgain)
+        ));
+        service.set_pose(
+            "bridge-test-connect",
+            T5_GlassesPose {
+                timestampNanos: 10,
+                posGLS_GBD: T5_Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 1.,
+                },
+                rotToGLS_GBD: T5_Quat {
+                    w: 1.,
+                    x: 0.,
+                    y: 0.,
+                    z: 0.,
+                },
+                gameboardType: T5_GameboardType_kT5_GameboardType_LE,
+            },
+        );
+        let pose = client.get_glasses_pose(&id).unwrap();
+        assert_eq!(pose.timestampNanos, 10);
+        assert_eq!(pose.posGLS_GBD.z, 1.);
+
+        service.connect_wand("bridge-test-connect", 3);
+        let events = client.get_wand_stream_events(&id).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].wandId, 3);
+        assert_eq!(client.list_wands(&id).unwrap(), vec![3]);
+
+        client.release_glasses(id.clone()).unwrap();
+        assert!(matches!(
+            client.get_glasses_pose(&id),
+            Err(T5Error::GlassesNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn reports_the_connection_state() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-state", "State");
+        let mut client = client();
+
+        client.create_glasses(&id).unwrap();
+        assert_eq!(
+            client.get_connection_state(&id),
+            Ok(GlassesConnectionState::ExclusiveConnection)
+        );
+
+        service.set_connection_state("bridge-test-state", GlassesConnectionState::Disconnected);
+        assert_eq!(
+            client.get_connection_state(&id),
+            Ok(GlassesConnectionState::Disconnected)
+        );
+    }
+
+    #[test]
+    fn reports_changed_glasses_params() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-params", "Params");
+        let mut client = client();
+
+        client.create_glasses(&id).unwrap();
+        client.get_changed_glasses_params(&id).unwrap();
+
+        service.set_ipd("bridge-test-params", 62.);
+        assert_eq!(
+            client.get_changed_glasses_params(&id),
+            Ok(vec![GlassesParam::Ipd])
+        );
+        assert_eq!(client.get_ipd(&id), Ok(62.));
+        assert_eq!(client.get_changed_glasses_params(&id), Ok(vec![]));
+    }
+
+    #[test]
+    fn connects_a_step_at_a_time() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-steps", "Steps");
+        let mut client = client();
+
+        service.inject_error(
+            "bridge-test-steps",
+            "t5EnsureGlassesReady",
+            T5_ERROR_TRY_AGAIN,
+            2,
+        );
+        let mut steps = vec![];
+        loop {
+            let progress = client.connect_glasses(&id).unwrap();
+            steps.push(progress.clone());
+            if progress == ConnectionProgress::Ready {
+                break;
+            }
+        }
+        assert_eq!(
+            steps,
+            vec![
+                ConnectionProgress::WaitingForReady,
+                ConnectionProgress::WaitingForReady,
+                ConnectionProgress::WaitingForReady,
+                ConnectionProgress::InitializingGraphics,
+                ConnectionProgress::ConfiguringWands,
+                ConnectionProgress::Ready,
+            ]
+        );
+        assert_eq!(client.get_ipd(&id).unwrap(), 59.);
+    }
+
+    #[test]
+    fn retries_only_the_step_that_asked_to() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-retry-step", "Retry Step");
+        let mut client = client();
+        let mut device = 0u8;
+        // The fake service never touches the device
+        unsafe {
+            client
+                .set_dx11_graphics_context(&mut device as *mut u8 as *mut std::ffi::c_void)
+                .unwrap();
+        }
+
+        // The graphics context isn't initialized twice when the wand stream
+        // asks to try again
+        service.inject_error(
+            "bridge-test-retry-step",
+            "t5ConfigureWandStreamForGlasses",
+            T5_ERROR_TRY_AGAIN,
+            1,
+        );
+        client.create_glasses(&id).unwrap();
+
+        // Setting the context again reaches glasses that are already connected
+        let other = service.add_glasses("bridge-test-late-context", "Late Context");
+        let mut client = T5Client::with_library(library_path(), "test", "1").unwrap();
+        client.create_glasses(&other).unwrap();
+        assert_eq!(
+            unsafe {
+                client.set_dx11_graphics_context(&mut device as *mut u8 as *mut std::ffi::c_void)
+            },
+            Ok(())
+        );
+        // Which the service only takes once
+        assert_eq!(
+            unsafe {
+                client.set_dx11_graphics_context(&mut device as *mut u8 as *mut std::ffi::c_void)
+            },
+            Err(T5Error::InvalidState)
+        );
+    }
+
+    #[test]
+    fn reads_system_params() {
+        let service = FakeService::new();
+        let mut client = client();
+
+        assert_eq!(client.get_service_version().unwrap(), "fake-1.0.0");
+        assert!(matches!(
+            client.get_system_float_param(SystemParam::ServiceVersion),
+            Err(T5Error::SettingWrongType)
+        ));
+
+        service.set_attention_required(true);
+        assert!(client
+            .get_changed_system_params()
+            .unwrap()
+            .contains(&SystemParam::AttentionRequired));
+        assert!(client.is_attention_required().unwrap());
+        service.set_attention_required(false);
+        assert!(!client.is_attention_required().unwrap());
+    }
+
+    #[test]
+    fn injected_errors_are_typed() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-errors", "Errors");
+        let mut client = client();
+
+        service.inject_error(
+            "bridge-test-errors",
+            "t5ReserveGlasses",
+            T5_ERROR_UNAVAILABLE,
+            1,
+        );
+        assert_eq!(client.create_glasses(&id), Err(T5Error::Unavailable));
+
+        service.inject_error(
+            "bridge-test-errors",
+            "t5ReserveGlasses",
+            T5_ERROR_SERVICE_INCOMPATIBLE,
+            1,
+        );
+        let err = client.create_glasses(&id).unwrap_err();
+        assert!(err.is_fatal());
+
+        assert!(client.create_glasses(&id).is_ok());
+    }
+
+    #[test]
+    fn reserves_glasses_with_the_configured_display_name() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-settings", "Settings");
+        let mut client = T5Client::with_settings(&TiltFiveSettings {
+            application_id: "settings-test".to_string(),
+            display_name_template: "{app} on {glasses}".to_string(),
+            library_path: Some(library_path()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        client.create_glasses(&id).unwrap();
+        assert_eq!(
+            service.display_name("bridge-test-settings"),
+            "settings-test on bridge-test-settings"
+        );
+    }
+
+    #[test]
+    fn sets_display_names_per_glasses() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-display-name", "Display Name");
+        let mut client = client();
+
+        client
+            .set_glasses_display_name(&id, "Player 2 - Red Team")
+            .unwrap();
+        client.create_glasses(&id).unwrap();
+        assert_eq!(
+            service.display_name("bridge-test-display-name"),
+            "Player 2 - Red Team"
+        );
+
+        client
+            .set_glasses_display_name(&id, "Player 2 - Blue Team")
+            .unwrap();
+        assert_eq!(
+            service.display_name("bridge-test-display-name"),
+            "Player 2 - Blue Team"
+        );
+    }
+
+    #[test]
+    fn streams_camera_images() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-camera", "Camera");
+        let mut client = client();
+        client.create_glasses(&id).unwrap();
+
+        // Rows of 3 pixels, padded out to 4 bytes
+        let image = T5_CamImage {
+            imageWidth: 3,
+            imageHeight: 2,
+            imageStride: 4,
+            cameraIndex: 0,
+            illuminationMode: 2,
+            bufferSize: 0,
+            pixelData: std::ptr::null_mut(),
+            posCAM_GBD: T5_Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.5,
+            },
+            rotToCAM_GBD: T5_Quat {
+                w: 1.,
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+        };
+        service.push_cam_image("bridge-test-camera", image, &[1, 2, 3, 0, 4, 5, 6, 0]);
+        // Nothing comes through until the camera is streaming
+        assert!(client.get_camera_images(&id).unwrap().is_empty());
+
+        client
+            .configure_camera_stream(&id, GlassesCamera::Tangible, true)
+            .unwrap();
+        let images = client.get_camera_images(&id).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].camera, GlassesCamera::Tangible);
+        assert_eq!(images[0].illumination, IlluminationMode::Dark);
+        assert_eq!(images[0].pixels, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(images[0].pixel(2, 1), 6);
+        assert_eq!(images[0].position.z, 0.5);
+
+        // Filled buffers are handed straight back to be filled again
+        for _ in 0..5 {
+            service.push_cam_image("bridge-test-camera", image, &[0; 8]);
+            assert_eq!(client.get_camera_images(&id).unwrap().len(), 1);
+        }
+
+        client
+            .configure_camera_stream(&id, GlassesCamera::Tangible, false)
+            .unwrap();
+        service.push_cam_image("bridge-test-camera", image, &[0; 8]);
+        assert!(client.get_camera_images(&id).unwrap().is_empty());
+        client.release_glasses(id).unwrap();
+    }
+
+    #[test]
+    fn replaces_camera_buffers_the_service_wouldnt_take_back() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-camera-pool", "Camera Pool");
+        let mut client = T5Client::with_settings(&TiltFiveSettings {
+            library_path: Some(library_path()),
+            camera_buffers: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        client.create_glasses(&id).unwrap();
+        client
+            .configure_camera_stream(&id, GlassesCamera::Tangible, true)
+            .unwrap();
+
+        let mut image: T5_CamImage = unsafe { std::mem::zeroed() };
+        image.imageWidth = 2;
+        image.imageHeight = 1;
+        image.imageStride = 2;
+        service.push_cam_image("bridge-test-camera-pool", image, &[1, 2]);
+        service.inject_error(
+            "bridge-test-camera-pool",
+            "t5SubmitEmptyCamImageBuffer",
+            T5_ERROR_INVALID_STATE,
+            1,
+        );
+        assert_eq!(client.get_camera_images(&id).unwrap().len(), 1);
+
+        // The only buffer was lost, but the stream keeps going
+        service.push_cam_image("bridge-test-camera-pool", image, &[3, 4]);
+        let images = client.get_camera_images(&id).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].pixels, vec![3, 4]);
+        client.release_glasses(id).unwrap();
+    }
+
+    #[test]
+    fn keeps_buffers_too_small_for_a_frame() {
+        let service = FakeService::new();
+        let id = service.add_glasses("bridge-test-small-buffer", "Small Buffer");
+        let mut client = client();
+        client.create_glasses(&id).unwrap();
+        client
+            .configure_camera_stream(&id, GlassesCamera::Tangible, true)
+            .unwrap();
+
+        let mut image: T5_CamImage = unsafe { std::mem::zeroed() };
+        image.imageWidth = T5_MIN_CAM_IMAGE_BUFFER_WIDTH as u16 + 1;
+        image.imageHeight = T5_MIN_CAM_IMAGE_BUFFER_HEIGHT as u16;
+        image.imageStride = image.imageWidth;
+        let pixels = vec![0; image.imageWidth as usize * image.imageHeight as usize];
+        service.push_cam_image("bridge-test-small-buffer", image, &pixels);
+        assert_eq!(
+            client.get_camera_images(&id),
+            Err(T5Error::InvalidBufferSize)
+        );
+
+        // Every buffer is still the service's to give back
+        client
+            .configure_camera_stream(&id, GlassesCamera::Tangible, false)
+            .unwrap();
+        client.release_glasses(id).unwrap();
+    }
 }
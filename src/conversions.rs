@@ -28,3 +28,35 @@ pub fn transform_matrix_from_bevy_to_glasses_space(
 
     (transform, transform_from_gameboard_to_glasses)
 }
+
+/// The inverse of `transform_matrix_from_bevy_to_glasses_space` - the position
+/// and rotation the SDK would report for glasses at `transform` on the board
+pub fn glasses_pose_from_bevy_transform(transform: &Transform) -> (Vec3, Quat) {
+    let transform_from_gameboard_to_world = Quat::from_rotation_x(PI / 2.);
+    let position = transform_from_gameboard_to_world * transform.translation;
+
+    let conversion = Quat::from_rotation_x(PI);
+    let rotation = (transform_from_gameboard_to_world * transform.rotation).conjugate();
+    let rotation = conversion.inverse() * rotation;
+
+    (position, rotation)
+}
+
+/// A pose the SDK reports in gameboard space - a wand's, say - as a transform
+/// under the board, converted the same way as the glasses pose. The inverse of
+/// `glasses_pose_from_bevy_transform`.
+pub fn bevy_transform_from_gameboard_pose(position: Vec3, rotation: Quat) -> Transform {
+    let transform_from_world_to_gameboard = Quat::from_rotation_x(-PI / 2.);
+
+    let conversion = Quat::from_rotation_x(PI);
+    let rotation = (conversion * rotation).conjugate();
+
+    Transform::from_translation(transform_from_world_to_gameboard * position)
+        .with_rotation(transform_from_world_to_gameboard * rotation)
+}
+
+/// A position in gameboard space, as the SDK reports it, in the space of the
+/// entities under the board
+pub fn bevy_position_from_gameboard(position: Vec3) -> Vec3 {
+    Quat::from_rotation_x(-PI / 2.) * position
+}
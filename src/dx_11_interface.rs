@@ -21,10 +21,8 @@ use winapi::um::d3d11::{
 };
 
 use crate::bridge::ffi::T5_FrameInfo__bindgen_ty_1;
-use crate::bridge::{
-    self, Glasses, DEFAULT_GLASSES_FOV, DEFAULT_GLASSES_HEIGHT, DEFAULT_GLASSES_WIDTH,
-};
-use crate::{BufferSender, GlassesBufferInfo, T5ClientRenderApp, TEXTURE_FORMAT};
+use crate::bridge::{self, Glasses, DEFAULT_GLASSES_FOV};
+use crate::{BufferSender, GlassesBufferInfo, T5ClientRenderApp, TiltFiveSettings};
 
 pub struct DX11Plugin;
 
@@ -106,13 +104,11 @@ struct DX11Buffer {
 fn setup_dx_11_interface(
     _device: Res<RenderDevice>,
     mut dx11resource: NonSendMut<DX11DeviceResource>,
-    mut client: NonSendMut<T5ClientRenderApp>,
+    client: NonSend<T5ClientRenderApp>,
 ) {
     if dx11resource.devices.is_none() {
         if let Ok(devices) = create_dx11_device() {
-            client
-                .client
-                .set_dx11_graphics_context(devices.device as *mut c_void);
+            client.set_dx11_graphics_context(devices.device as *mut c_void);
             dx11resource.devices = Some(devices);
         } else {
             error!("Couldn't setup dx11 device...");
@@ -122,12 +118,14 @@ fn setup_dx_11_interface(
 
 fn send_frames(
     resource: NonSendMut<DX11DeviceResource>,
-    mut client: NonSendMut<T5ClientRenderApp>,
+    client: NonSend<T5ClientRenderApp>,
     mut buffer: NonSendMut<DX11Buffer>,
+    settings: Res<TiltFiveSettings>,
 ) {
-    let fmt = TEXTURE_FORMAT.describe();
-    let bytes_per_row =
-        DEFAULT_GLASSES_WIDTH * (fmt.block_dimensions.0 as u32) * (fmt.block_size as u32);
+    let width = settings.render_resolution.x;
+    let height = settings.render_resolution.y;
+    // The rows read back from the GPU are padded out to wgpu's alignment
+    let bytes_per_row = settings.bytes_per_row();
 
     buffer.current_frame_is_odd = !buffer.current_frame_is_odd;
 
@@ -144,8 +142,8 @@ fn send_frames(
                 let mut right_tex = MaybeUninit::uninit();
 
                 let description = D3D11_TEXTURE2D_DESC {
-                    Width: DEFAULT_GLASSES_WIDTH,
-                    Height: DEFAULT_GLASSES_HEIGHT,
+                    Width: width,
+                    Height: height,
                     MipLevels: 1,
                     ArraySize: 1,
                     Format: DXGI_FORMAT_R8G8B8A8_TYPELESS,
@@ -182,16 +180,15 @@ fn send_frames(
 
                 let start_y_vci =
                     -1.0 * (DEFAULT_GLASSES_FOV * 0.5 * std::f32::consts::PI / 180.).tan();
-                let start_x_vci =
-                    start_y_vci * (DEFAULT_GLASSES_WIDTH as f32 / DEFAULT_GLASSES_HEIGHT as f32);
+                let start_x_vci = start_y_vci * (width as f32 / height as f32);
                 let width_vci = -2.0 * start_x_vci;
                 let height_vci = -2.0 * start_y_vci;
 
                 let frame_info = bridge::ffi::T5_FrameInfo {
                     leftTexHandle: *left_tex.as_mut_ptr() as *mut c_void,
                     rightTexHandle: *right_tex.as_mut_ptr() as *mut c_void,
-                    texWidth_PIX: DEFAULT_GLASSES_WIDTH as u16,
-                    texHeight_PIX: DEFAULT_GLASSES_HEIGHT as u16,
+                    texWidth_PIX: width as u16,
+                    texHeight_PIX: height as u16,
                     isSrgb: false,
                     isUpsideDown: true,
                     rotToLVC_GBD: rot,
@@ -208,7 +205,7 @@ fn send_frames(
 
                 let info = MaybeUninit::new(frame_info);
 
-                let _ = client.client.send_frame_to_glasses(&glasses, info.as_ptr());
+                client.send_frame_to_glasses(&glasses, info.as_ptr());
 
                 current_buffer.insert(glasses.clone(), (left_tex, right_tex));
             }
@@ -6,10 +6,7 @@ use bevy::{
 };
 use wgpu::{ImageCopyBuffer, ImageDataLayout};
 
-use crate::{
-    bridge::{DEFAULT_GLASSES_HEIGHT, DEFAULT_GLASSES_WIDTH},
-    T5RenderGlassesList, GLASSES_TEXTURE_SIZE, TEXTURE_FORMAT,
-};
+use crate::{T5RenderGlassesList, TiltFiveSettings};
 
 pub const EYE_CLONE_NODE_NAME: &str = "eye_clone_node";
 
@@ -24,10 +21,9 @@ impl Node for EyeCloneNode {
         world: &bevy::prelude::World,
     ) -> Result<(), bevy::render::render_graph::NodeRunError> {
         let list = world.resource::<T5RenderGlassesList>();
-        let format = TEXTURE_FORMAT;
-        let fmt = format.describe();
-        let bytes_per_row =
-            DEFAULT_GLASSES_WIDTH * (fmt.block_dimensions.0 as u32) * (fmt.block_size as u32);
+        let settings = world.resource::<TiltFiveSettings>();
+        let texture_size = settings.texture_size();
+        let bytes_per_row = settings.bytes_per_row();
 
         for (_, (_, images, buffers, _)) in list.glasses.iter() {
             if let (Some((left, right)), Some((lb, rb))) = (&images, &buffers) {
@@ -39,12 +35,10 @@ impl Node for EyeCloneNode {
                             layout: ImageDataLayout {
                                 offset: 0,
                                 bytes_per_row: Some(NonZeroU32::new(bytes_per_row).unwrap()),
-                                rows_per_image: Some(
-                                    NonZeroU32::new(DEFAULT_GLASSES_HEIGHT).unwrap(),
-                                ),
+                                rows_per_image: Some(NonZeroU32::new(texture_size.height).unwrap()),
                             },
                         },
-                        GLASSES_TEXTURE_SIZE,
+                        texture_size,
                     );
                 }
                 if let Some(image) = world.resource::<RenderAssets<Image>>().get(right) {
@@ -55,12 +49,10 @@ impl Node for EyeCloneNode {
                             layout: ImageDataLayout {
                                 offset: 0,
                                 bytes_per_row: Some(NonZeroU32::new(bytes_per_row).unwrap()),
-                                rows_per_image: Some(
-                                    NonZeroU32::new(DEFAULT_GLASSES_HEIGHT).unwrap(),
-                                ),
+                                rows_per_image: Some(NonZeroU32::new(texture_size.height).unwrap()),
                             },
                         },
-                        GLASSES_TEXTURE_SIZE,
+                        texture_size,
                     );
                 }
             }
@@ -1,18 +1,32 @@
+mod backend;
 mod bridge;
 
 mod conversions;
 #[cfg(target_family = "windows")]
 mod dx_11_interface;
 mod eye_clone_node;
+mod pose_filter;
+mod pose_history;
+mod settings;
+mod simulator;
+mod tracking;
+mod viewable_area;
+mod wand;
+mod worker;
 
 use std::{
     f32::consts::PI,
-    sync::mpsc::{channel, Receiver, Sender},
+    ffi::c_void,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
     time::Duration,
 };
 
 use bevy::{
     core_pipeline::tonemapping::Tonemapping,
+    input::{gamepad::GamepadEventRaw, InputSystem},
     prelude::*,
     render::{
         camera::RenderTarget,
@@ -25,96 +39,283 @@ use bevy::{
         renderer::RenderDevice,
         RenderApp, RenderStage,
     },
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bridge::{
-    ffi::{T5_Quat, T5_Vec3, T5_WandReport},
+    ffi::{T5_Quat, T5_Vec3},
     *,
 };
 
+#[cfg(any(test, feature = "fake"))]
+pub use backend::{FakeBackend, FakeGlasses};
+pub use backend::{
+    RecordedFrame, RecordedSample, Recording, RecordingBackend, ReplayBackend, TiltFiveBackend,
+    RECORDING_VERSION,
+};
+// The SDK types that backends trade in
+pub use bridge::ffi::{
+    T5_FrameInfo, T5_GameboardSize, T5_GlassesPose, T5_WandHandle, T5_WandReport,
+    T5_WandStreamEvent,
+};
+pub use bridge::CameraImage;
+pub use bridge::ConnectionProgress;
 pub use bridge::Glasses;
+pub use bridge::GlassesCamera;
+pub use bridge::GlassesConnectionState;
+pub use bridge::GlassesParam;
+pub use bridge::IlluminationMode;
+pub use bridge::SystemParam;
+pub use bridge::T5Client;
+pub use bridge::T5Error;
 pub use bridge::T5GameboardType;
+pub use bridge::T5Result;
+pub use bridge::{library_candidates, library_name, LIBRARY_PATH_VAR};
+pub use pose_filter::{FilteredPose, PoseFilter};
+pub use pose_history::{PoseHistory, TimedPose};
+pub use settings::{GlassesPoseUsage, ReconnectPolicy, RetryPolicy, TiltFiveSettings};
+pub use simulator::{TiltFiveSimulator, TiltFiveSimulatorPlugin, SIMULATED_GLASSES_ID};
+pub use tracking::{
+    blob_board_position, find_blobs, Blob, BlobTrackingSettings, TiltFiveTrackingPlugin,
+    TrackedBlob,
+};
+pub use viewable_area::{
+    EnteredViewableArea, LeftViewableArea, OutOfBounds, TiltFiveViewableAreaPlugin,
+    ViewableAreaContent,
+};
+pub use wand::{
+    Hand, WandAnalog, WandAxis, WandAxisType, WandButton, WandButtonType, WandButtons, WandGamepad,
+    WandGamepads, WandPose, WandReport, WAND_GAMEPAD_ID_START,
+};
 use wgpu::{BufferDescriptor, BufferUsages, MapMode};
+use worker::{FrameInfo, GraphicsDevice, PoseSnapshot, WorkerMessage};
+
+type BackendFactory = dyn Fn(&TiltFiveSettings) -> T5Result<Box<dyn TiltFiveBackend>> + Send + Sync;
 
-use crate::conversions::transform_matrix_from_bevy_to_glasses_space;
+pub struct TiltFivePlugin {
+    pub settings: TiltFiveSettings,
+    backend: Arc<BackendFactory>,
+}
+
+impl Default for TiltFivePlugin {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl TiltFivePlugin {
+    pub fn new(settings: TiltFiveSettings) -> Self {
+        Self {
+            settings,
+            backend: Arc::new(|settings| {
+                T5Client::with_settings(settings)
+                    .map(|client| Box::new(client) as Box<dyn TiltFiveBackend>)
+            }),
+        }
+    }
 
-pub struct TiltFivePlugin;
+    /// Run the plugin against any `TiltFiveBackend`, created when the plugin is built
+    pub fn with_backend<B, F>(settings: TiltFiveSettings, backend: F) -> Self
+    where
+        B: TiltFiveBackend + 'static,
+        F: Fn() -> T5Result<B> + Send + Sync + 'static,
+    {
+        Self {
+            settings,
+            backend: Arc::new(move |_| {
+                backend().map(|backend| Box::new(backend) as Box<dyn TiltFiveBackend>)
+            }),
+        }
+    }
+}
 
 impl Plugin for TiltFivePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<TiltFiveClientEvent>()
             .add_event::<TiltFiveCommands>()
             .init_resource::<AvailableGlasses>()
-            .register_type::<AvailableGlasses>();
-
-        if let Ok(client) = T5Client::new("my-app", "1") {
-            println!("Setting up T5 Client");
-            let (command_sender, command_receiver) = channel();
-            let (event_sender, event_receiver) = channel();
-
-            let main_app_client = T5ClientMainApp {
-                sender: command_sender,
-                receiver: event_receiver,
-            };
-
-            let render_app_client = T5ClientRenderApp {
-                client,
-                sender: event_sender,
-                receiver: command_receiver,
-            };
-
-            app.insert_non_send_resource(main_app_client)
-                .add_system(setup_board_transformer)
-                .add_system(setup_debug_gizmo)
-                .add_system(communicate_with_client)
-                .add_system(update_glasses_list)
-                .add_system(connect_to_glasses)
-                .add_system(disconnect_from_glasses)
-                .add_system(setup_glasses_rendering)
-                .add_system(set_glasses_position)
-                .add_system(adjust_glasses_position);
-
-            app.add_system(setup_debug_meshes);
-
-            let render_app = app.sub_app_mut(RenderApp);
-            render_app
-                .insert_non_send_resource(render_app_client)
-                .insert_resource(T5RenderGlassesList {
-                    glasses: Default::default(),
-                })
-                .add_system_to_stage(RenderStage::Extract, get_glasses_pose)
-                .add_system_to_stage(RenderStage::Extract, process_commands)
-                .add_system_to_stage(RenderStage::Prepare, setup_buffers_for_frame)
-                .add_system_to_stage(RenderStage::Cleanup, retrieve_textures_from_gpu);
-
-            let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
-
-            graph.add_node(
-                eye_clone_node::EYE_CLONE_NODE_NAME,
-                eye_clone_node::EyeCloneNode::default(),
-            );
-            graph
-                .add_node_edge(CAMERA_DRIVER, eye_clone_node::EYE_CLONE_NODE_NAME)
-                .unwrap();
+            .register_type::<AvailableGlasses>()
+            .init_resource::<TiltFiveService>()
+            .register_type::<TiltFiveService>()
+            .register_type::<SystemParam>()
+            .register_type::<GlassesParam>()
+            .register_type::<GlassesParamValue>()
+            .register_type::<T5Error>()
+            .register_type::<GlassesConnectionState>()
+            .register_type::<ConnectionProgress>()
+            .register_type::<GlassesCamera>()
+            .register_type::<IlluminationMode>()
+            .register_type::<GlassesCameraFrame>()
+            .register_type::<T5GameboardType>()
+            .register_type::<Gameboard>()
+            .register_type::<TiltFiveSpectatorCamera>()
+            .register_type::<TiltFiveWand>()
+            .register_type::<WandReport>()
+            .register_type::<WandAnalog>()
+            .register_type::<WandButtons>()
+            .register_type::<WandPose>()
+            .register_type::<Hand>()
+            .register_type::<WandButton>()
+            .register_type::<WandButtonType>()
+            .register_type::<WandAxis>()
+            .register_type::<WandAxisType>()
+            .init_resource::<Input<WandButton>>()
+            .init_resource::<Axis<WandAxis>>()
+            .register_type::<WandGamepad>()
+            .init_resource::<WandGamepads>()
+            .register_type::<WandGamepads>()
+            .init_resource::<PendingWands>()
+            // Already there with the `InputPlugin`, but headless apps without
+            // it still need somewhere to send wand gamepad events
+            .add_event::<GamepadEventRaw>()
+            .register_type::<TimedPose>()
+            .register_type::<PoseHistory>()
+            .register_type::<PoseFilter>()
+            .register_type::<FilteredPose>()
+            .insert_resource(self.settings.clone())
+            .register_type::<TiltFiveSettings>();
+
+        match worker::spawn(self.backend.clone(), self.settings.clone()) {
+            Err(err) => error!("Couldn't create T5 Client: {err}"),
+            Ok(worker) => {
+                println!("Setting up T5 Client");
+                let main_app_client = T5ClientMainApp {
+                    sender: worker.sender.clone(),
+                    receiver: worker.receiver,
+                };
 
-            #[cfg(target_family = "windows")]
-            {
-                app.add_plugin(dx_11_interface::DX11Plugin);
-                // app.add_plugin(ogl_interface::OGLPlugin);
+                let render_app_client = T5ClientRenderApp {
+                    sender: worker.sender,
+                    snapshot: worker.snapshot,
+                };
+
+                app.insert_non_send_resource(main_app_client)
+                    .add_system(setup_board_transformer)
+                    .add_system(setup_debug_gizmo)
+                    .add_system(communicate_with_client)
+                    .add_system(update_glasses_list)
+                    // Glasses have to be in the list before the events that
+                    // arrive with them are handled
+                    .add_system(connect_to_glasses.after(communicate_with_client))
+                    .add_system(disconnect_from_glasses)
+                    .add_system(setup_glasses_rendering)
+                    .add_system(
+                        set_glasses_position
+                            .after(connect_to_glasses)
+                            .before(disconnect_from_glasses),
+                    )
+                    .add_system(
+                        record_glasses_poses
+                            .after(connect_to_glasses)
+                            .before(disconnect_from_glasses),
+                    )
+                    .add_system(pose_filter::filter_poses.after(record_glasses_poses))
+                    .add_system(
+                        set_glasses_connection_state
+                            .after(connect_to_glasses)
+                            .before(disconnect_from_glasses),
+                    )
+                    .add_system(
+                        set_glasses_params
+                            .after(connect_to_glasses)
+                            .before(disconnect_from_glasses),
+                    )
+                    .add_system(
+                        update_camera_frames
+                            .after(connect_to_glasses)
+                            .before(disconnect_from_glasses),
+                    )
+                    .add_system(update_gameboard)
+                    .add_system(track_spectators)
+                    .add_system(update_spectator_cameras)
+                    .add_system(update_wands)
+                    .add_system_to_stage(CoreStage::PreUpdate, wand::update_wand_input)
+                    .add_system_to_stage(
+                        CoreStage::PreUpdate,
+                        wand::update_wand_gamepads.before(InputSystem),
+                    )
+                    .add_system(recover_glasses)
+                    .add_system(update_service)
+                    .add_system(adjust_glasses_position);
+
+                app.add_system(setup_debug_meshes);
+
+                // Without a renderer (headless apps, CI) there are no frames to
+                // send - the worker talks to the service either way
+                let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+                    return;
+                };
+                render_app
+                    .insert_non_send_resource(render_app_client)
+                    .insert_resource(self.settings.clone())
+                    .init_resource::<T5RenderGlassesList>()
+                    .add_system_to_stage(RenderStage::Extract, update_glasses_from_worker)
+                    .add_system_to_stage(RenderStage::Prepare, setup_buffers_for_frame)
+                    .add_system_to_stage(RenderStage::Cleanup, retrieve_textures_from_gpu);
+
+                let mut graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+
+                graph.add_node(
+                    eye_clone_node::EYE_CLONE_NODE_NAME,
+                    eye_clone_node::EyeCloneNode::default(),
+                );
+                graph
+                    .add_node_edge(CAMERA_DRIVER, eye_clone_node::EYE_CLONE_NODE_NAME)
+                    .unwrap();
+
+                #[cfg(target_family = "windows")]
+                {
+                    app.add_plugin(dx_11_interface::DX11Plugin);
+                    // app.add_plugin(ogl_interface::OGLPlugin);
+                }
             }
         }
     }
 }
 
 struct T5ClientMainApp {
-    pub sender: Sender<TiltFiveCommands>,
+    pub sender: Sender<WorkerMessage>,
     pub receiver: Receiver<TiltFiveClientEvent>,
 }
 
+/// The render world's way to the worker - it only waits on the worker to send
+/// a frame, never on the service behind it
 struct T5ClientRenderApp {
-    client: T5Client,
-    sender: Sender<TiltFiveClientEvent>,
-    receiver: Receiver<TiltFiveCommands>,
+    sender: Sender<WorkerMessage>,
+    snapshot: PoseSnapshot,
+}
+
+impl T5ClientRenderApp {
+    #[cfg_attr(not(target_family = "windows"), allow(dead_code))]
+    fn set_dx11_graphics_context(&self, device: *mut c_void) {
+        let _ = self
+            .sender
+            .send(WorkerMessage::SetDx11GraphicsContext(GraphicsDevice(
+                device,
+            )));
+    }
+
+    /// Have the worker send a frame to the glasses, waiting until it has so
+    /// the textures are done with before this returns
+    ///
+    /// # Safety
+    ///
+    /// `info` has to point to a valid frame, and the textures it refers to
+    /// have to be alive until this returns
+    #[cfg_attr(not(target_family = "windows"), allow(dead_code))]
+    unsafe fn send_frame_to_glasses(&self, glasses: &Glasses, info: *const T5_FrameInfo) {
+        let (sent, frame_sent) = channel();
+        if self
+            .sender
+            .send(WorkerMessage::SendFrame(
+                glasses.clone(),
+                FrameInfo(*info),
+                sent,
+            ))
+            .is_ok()
+        {
+            let _ = frame_sent.recv();
+        }
+    }
 }
 
 type GlassesMapData = (
@@ -124,7 +325,7 @@ type GlassesMapData = (
     Option<(T5_Vec3, T5_Vec3, T5_Quat)>,
 );
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 struct T5RenderGlassesList {
     glasses: HashMap<Glasses, GlassesMapData>,
 }
@@ -132,6 +333,7 @@ struct T5RenderGlassesList {
 #[derive(Bundle, Default)]
 pub struct BoardBundle {
     pub board: Board,
+    pub gameboard: Gameboard,
     pub spatial: SpatialBundle,
 }
 
@@ -169,9 +371,73 @@ pub struct AvailableGlasses {
     pub glasses: HashMap<Glasses, GlassesInfo>,
 }
 
+/// What the plugin knows about the Tilt Five service itself
+#[derive(Resource, Reflect, FromReflect, Debug, Default, Clone, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct TiltFiveService {
+    /// `None` until the service reports it
+    pub version: Option<String>,
+    /// The Tilt Five control panel needs the user's attention - for a firmware
+    /// update, for example - and the glasses may not work until it gets it
+    pub attention_required: bool,
+}
+
 #[derive(Component)]
 pub struct Board(f32);
 
+/// The board the glasses last saw, and how much of the table content can be
+/// seen on - kept up to date on the `Board` entity.
+#[derive(Component, Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct Gameboard {
+    pub board_type: T5GameboardType,
+    /// The corner of the viewable area furthest left, lowest and furthest from
+    /// the player, in the board's space - in meters
+    pub min: Vec3,
+    /// The corner furthest right, highest and closest to the player
+    pub max: Vec3,
+}
+
+impl Gameboard {
+    pub fn new(board_type: T5GameboardType, size: &T5_GameboardSize) -> Self {
+        let min = conversions::bevy_position_from_gameboard(Vec3::new(
+            -size.viewableExtentNegativeX,
+            -size.viewableExtentNegativeY,
+            0.,
+        ));
+        let max = conversions::bevy_position_from_gameboard(Vec3::new(
+            size.viewableExtentPositiveX,
+            size.viewableExtentPositiveY,
+            size.viewableExtentPositiveZ,
+        ));
+        Self {
+            board_type,
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    /// The width, height and depth of the viewable area
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// How far `position`, in the board's space, is inside the viewable area -
+    /// negative once it's outside. Content below the surface is seen through
+    /// the board, and flat boards report no height, so only the edges and the
+    /// top of a raised board bound it.
+    pub fn distance_inside(&self, position: Vec3) -> f32 {
+        let mut distance = (position.x - self.min.x)
+            .min(self.max.x - position.x)
+            .min(position.z - self.min.z)
+            .min(self.max.z - position.z);
+        if self.max.y > self.min.y {
+            distance = distance.min(self.max.y - position.y);
+        }
+        distance
+    }
+}
+
 impl Default for Board {
     fn default() -> Self {
         Self(1.)
@@ -183,7 +449,30 @@ pub enum TiltFiveClientEvent {
     GlassesFound(Vec<Glasses>),
     GlassesConnected(Glasses, Option<String>),
     GlassesDisconnected(Glasses),
-    GlassesPoseChanged(Glasses, Transform, f32, Transform),
+    /// The glasses moved - with their transform under the board, IPD, pose in
+    /// gameboard space and the SDK's timestamp for the pose
+    GlassesPoseChanged(Glasses, Transform, f32, Transform, Duration),
+    /// The smoothed spectator pose changed, as a transform under the board
+    /// like the one in `GlassesPoseChanged`
+    SpectatorPoseChanged(Glasses, Transform),
+    GlassesConnectionStateChanged(Glasses, GlassesConnectionState),
+    /// The glasses went away while connected - their entity stays around
+    /// while the plugin tries to reconnect
+    GlassesLost(Glasses),
+    /// Lost glasses are connected again, with the friendly name they came back with
+    GlassesRecovered(Glasses, Option<String>),
+    GlassesConnectionProgress(Glasses, ConnectionProgress),
+    /// The IPD or friendly name of connected glasses changed - also sent with
+    /// the IPD when glasses connect
+    GlassesParamChanged(Glasses, GlassesParamValue),
+    /// The service version or control panel attention flag changed - the
+    /// `TiltFiveService` resource is updated to match
+    ServiceChanged(TiltFiveService),
+    /// The glasses see a different kind of board than before - the `Gameboard`
+    /// on the board entity is updated to match
+    GameboardChanged(Glasses, Gameboard),
+    /// A frame from a camera streamed with `TiltFiveCommands::SetCameraStream`
+    CameraImage(Glasses, CameraImage),
     WandConnected {
         glasses: Glasses,
         wand_id: String,
@@ -199,16 +488,32 @@ pub enum TiltFiveClientEvent {
     WantReportUpdated {
         glasses: Glasses,
         wand_id: String,
-        report: T5_WandReport,
+        report: WandReport,
     },
 }
 
+/// The new value of a glasses param
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq)]
+pub enum GlassesParamValue {
+    /// In meters
+    Ipd(f32),
+    FriendlyName(Option<String>),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TiltFiveCommands {
     RefreshGlassesList,
     ConnectToGlasses(Glasses),
+    /// Connect to the glasses, showing this name in the Tilt Five control panel
+    /// instead of the one from `TiltFiveSettings::display_name_template`
+    ConnectToGlassesWithDisplayName(Glasses, String),
     DisconnectFromGlasses(Glasses),
     SetGlassesImages(Glasses, Handle<Image>, Handle<Image>),
+    /// Change the name the glasses show in the Tilt Five control panel
+    SetGlassesDisplayName(Glasses, String),
+    /// Start or stop streaming frames from one of the glasses' cameras - the
+    /// latest frame ends up in a `GlassesCameraFrame` under the glasses
+    SetCameraStream(Glasses, GlassesCamera, bool),
 }
 
 #[derive(Component)]
@@ -220,6 +525,48 @@ struct TiltFiveIPD(f32);
 #[derive(Component)]
 pub struct DebugGizmo(pub Color);
 
+/// The latest frame from one of the glasses' cameras.
+///
+/// Each streaming camera gets a child entity of its glasses with this on it,
+/// updated in place as new frames arrive.
+#[derive(Component, Reflect, FromReflect, Debug, Clone)]
+pub struct GlassesCameraFrame {
+    pub glasses: Glasses,
+    pub camera: GlassesCamera,
+    /// A single channel image, replaced with each new frame
+    pub image: Handle<Image>,
+    pub illumination: IlluminationMode,
+    /// Where the camera was when the frame was taken, in gameboard space
+    pub position: Vec3,
+    /// The rotation from gameboard space to the camera
+    pub rotation: Quat,
+}
+
+/// Drives a regular camera - the main window's, say - from the smoothed pose
+/// the SDK provides for spectators, so an audience sees what the wearer of
+/// `glasses` sees without the jitter of the glasses' own pose.
+///
+/// The camera's `Transform` is overwritten in world space, so it shouldn't be
+/// the child of another entity.
+#[derive(Component, Reflect, FromReflect, Debug, Clone)]
+pub struct TiltFiveSpectatorCamera {
+    pub glasses: Glasses,
+}
+
+/// A wand paired with a pair of glasses, kept under the board at the wand's
+/// grip. Spawned when the wand connects and despawned when it disconnects or
+/// its glasses do.
+#[derive(Component, Reflect, FromReflect, Debug, Clone)]
+pub struct TiltFiveWand {
+    pub glasses: Glasses,
+    pub wand_id: String,
+}
+
+/// Wands that connected before there was a board to put them under, by their
+/// glasses and wand id
+#[derive(Resource, Default)]
+struct PendingWands(HashSet<(Glasses, String)>);
+
 fn setup_board_transformer(mut commands: Commands, boards: Query<Entity, Added<Board>>) {
     for entity in boards.iter() {
         commands.entity(entity).with_children(|p| {
@@ -306,7 +653,7 @@ fn communicate_with_client(
     mut events: EventWriter<TiltFiveClientEvent>,
 ) {
     for command in commands.iter() {
-        let _ = client.sender.send(command.clone());
+        let _ = client.sender.send(WorkerMessage::Command(command.clone()));
     }
 
     while let Ok(event) = client.receiver.try_recv() {
@@ -314,47 +661,23 @@ fn communicate_with_client(
     }
 }
 
-fn process_commands(
-    mut client: NonSendMut<T5ClientRenderApp>,
+/// Pick up the glasses images and eye positions the worker last saw, without
+/// waiting if it's busy writing them
+fn update_glasses_from_worker(
+    client: NonSend<T5ClientRenderApp>,
     mut list: ResMut<T5RenderGlassesList>,
-    _device: Res<RenderDevice>,
 ) {
-    while let Ok(command) = client.receiver.try_recv() {
-        match command {
-            TiltFiveCommands::RefreshGlassesList => {
-                if let Ok(new_list) = client.client.list_glasses() {
-                    let _ = client
-                        .sender
-                        .send(TiltFiveClientEvent::GlassesFound(new_list));
-                }
-            }
-            TiltFiveCommands::ConnectToGlasses(glasses_id) => {
-                if !list.glasses.contains_key(&glasses_id) {
-                    if let Ok((glasses, friendly_name)) = client.client.create_glasses(&glasses_id)
-                    {
-                        list.glasses
-                            .insert(glasses_id.clone(), (glasses, None, None, None));
-                        let _ = client.sender.send(TiltFiveClientEvent::GlassesConnected(
-                            glasses_id,
-                            friendly_name,
-                        ));
-                    }
-                }
-            }
-            TiltFiveCommands::DisconnectFromGlasses(glasses_id) => {
-                if let Some((glasses, _, _, _)) = list.glasses.remove(&glasses_id) {
-                    let _ = client.client.release_glasses(glasses);
-                    let _ = client
-                        .sender
-                        .send(TiltFiveClientEvent::GlassesDisconnected(glasses_id));
-                }
-            }
-            TiltFiveCommands::SetGlassesImages(id, left, right) => {
-                if let Some(value) = list.glasses.get_mut(&id) {
-                    value.1 = Some((left, right));
-                }
-            }
-        }
+    let Some(snapshot) = client.snapshot.try_read() else {
+        return;
+    };
+    list.glasses.retain(|id, _| snapshot.contains_key(id));
+    for (id, glasses) in snapshot {
+        let value = list
+            .glasses
+            .entry(id.clone())
+            .or_insert_with(|| (id, None, None, None));
+        value.1 = glasses.images;
+        value.3 = glasses.eyes;
     }
 }
 
@@ -387,7 +710,9 @@ fn connect_to_glasses(
     mut events: EventReader<TiltFiveClientEvent>,
     mut commands: Commands,
     mut assets: ResMut<Assets<Image>>,
+    settings: Res<TiltFiveSettings>,
 ) {
+    let texture_size = settings.texture_size();
     for evt in events.iter() {
         if let TiltFiveClientEvent::GlassesConnected(glasses_id, friendly_name) = evt {
             if let Some(GlassesInfo::Disconnected) = list.glasses.get(glasses_id) {
@@ -395,7 +720,7 @@ fn connect_to_glasses(
                 let mut left = Image {
                     texture_descriptor: TextureDescriptor {
                         label: None,
-                        size: GLASSES_TEXTURE_SIZE,
+                        size: texture_size,
                         dimension: TextureDimension::D2,
                         format: TEXTURE_FORMAT,
                         mip_level_count: 1,
@@ -407,11 +732,11 @@ fn connect_to_glasses(
                     },
                     ..default()
                 };
-                left.resize(GLASSES_TEXTURE_SIZE);
+                left.resize(texture_size);
                 let mut right = Image {
                     texture_descriptor: TextureDescriptor {
                         label: None,
-                        size: GLASSES_TEXTURE_SIZE,
+                        size: texture_size,
                         dimension: TextureDimension::D2,
                         format: TEXTURE_FORMAT,
                         mip_level_count: 1,
@@ -423,7 +748,7 @@ fn connect_to_glasses(
                     },
                     ..default()
                 };
-                right.resize(GLASSES_TEXTURE_SIZE);
+                right.resize(texture_size);
 
                 let left = assets.add(left);
                 let right = assets.add(right);
@@ -431,6 +756,7 @@ fn connect_to_glasses(
                     .spawn((
                         SpatialBundle::default(),
                         TiltFiveGlasses(Some((glasses_id.clone(), left.clone(), right.clone()))),
+                        PoseHistory::new(settings.pose_history),
                     ))
                     .id();
                 list.glasses.insert(
@@ -535,9 +861,13 @@ fn setup_glasses_rendering(
     }
 }
 
+/// The first IPD usually arrives before the eye cameras are spawned, so they're
+/// placed again once they are
+type IpdOrEyesChanged = Or<(Changed<TiltFiveIPD>, Changed<Children>)>;
+
 fn adjust_glasses_position(
     mut cameras: Query<(&Camera3d, &mut Transform)>,
-    parents: Query<(&TiltFiveIPD, &Children), Changed<TiltFiveIPD>>,
+    parents: Query<(&TiltFiveIPD, &Children), IpdOrEyesChanged>,
 ) {
     for (ipd, children) in parents.iter() {
         let ipd = ipd.0 / 2.;
@@ -549,69 +879,322 @@ fn adjust_glasses_position(
     }
 }
 
-fn get_glasses_pose(
-    mut client: NonSendMut<T5ClientRenderApp>,
-    mut list: ResMut<T5RenderGlassesList>,
+fn set_glasses_position(
+    mut commands: Commands,
+    list: Res<AvailableGlasses>,
+    mut events: EventReader<TiltFiveClientEvent>,
 ) {
-    for (id, mut value) in list.glasses.iter_mut() {
-        match (
-            client.client.get_glasses_pose(&value.0),
-            client.client.get_ipd(&value.0),
-        ) {
-            (Ok(pose), Ok(ipd)) => {
-                let (transform, org) = transform_matrix_from_bevy_to_glasses_space(&pose);
-
-                let ipd = ipd * 0.001;
-                let lpos = org.left() * ipd + org.translation;
-                let rpos = org.right() * ipd + org.translation;
-
-                let _ = client.sender.send(TiltFiveClientEvent::GlassesPoseChanged(
-                    id.clone(),
-                    transform,
-                    ipd,
-                    org,
-                ));
+    for event in events.iter() {
+        if let TiltFiveClientEvent::GlassesPoseChanged(id, transform, _, _, _) = event {
+            if let Some(GlassesInfo::Connected {
+                entity,
+                left: _,
+                right: _,
+                friendly_name: _,
+            }) = list.glasses.get(id)
+            {
+                commands.entity(*entity).insert(*transform);
+            }
+        }
+    }
+}
+
+fn record_glasses_poses(
+    list: Res<AvailableGlasses>,
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut histories: Query<&mut PoseHistory>,
+) {
+    for event in events.iter() {
+        if let TiltFiveClientEvent::GlassesPoseChanged(id, transform, _, _, timestamp) = event {
+            if let Some(GlassesInfo::Connected { entity, .. }) = list.glasses.get(id) {
+                if let Ok(mut history) = histories.get_mut(*entity) {
+                    history.push(*timestamp, *transform);
+                }
+            }
+        }
+    }
+}
+
+fn set_glasses_connection_state(
+    mut commands: Commands,
+    list: Res<AvailableGlasses>,
+    mut events: EventReader<TiltFiveClientEvent>,
+) {
+    for event in events.iter() {
+        if let TiltFiveClientEvent::GlassesConnectionStateChanged(id, state) = event {
+            if let Some(GlassesInfo::Connected { entity, .. }) = list.glasses.get(id) {
+                commands.entity(*entity).insert(*state);
+            }
+        }
+    }
+}
+
+fn set_glasses_params(
+    mut commands: Commands,
+    mut list: ResMut<AvailableGlasses>,
+    mut events: EventReader<TiltFiveClientEvent>,
+) {
+    for event in events.iter() {
+        let TiltFiveClientEvent::GlassesParamChanged(id, value) = event else {
+            continue;
+        };
+        let Some(GlassesInfo::Connected {
+            entity,
+            friendly_name,
+            ..
+        }) = list.glasses.get_mut(id)
+        else {
+            continue;
+        };
+        match value {
+            GlassesParamValue::Ipd(ipd) => {
+                commands.entity(*entity).insert(TiltFiveIPD(*ipd));
+            }
+            GlassesParamValue::FriendlyName(name) => *friendly_name = name.clone(),
+        }
+    }
+}
+
+fn update_camera_frames(
+    mut commands: Commands,
+    list: Res<AvailableGlasses>,
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut frames: Query<&mut GlassesCameraFrame>,
+    children: Query<&Children>,
+    mut assets: ResMut<Assets<Image>>,
+) {
+    // Only the latest frame from each camera is worth turning into an image
+    let mut latest = HashMap::new();
+    for event in events.iter() {
+        if let TiltFiveClientEvent::CameraImage(id, image) = event {
+            latest.insert((id.clone(), image.camera), image);
+        }
+    }
 
-                let lpos = T5_Vec3 {
-                    x: lpos.x,
-                    y: lpos.y,
-                    z: lpos.z,
+    for ((id, camera), image) in latest {
+        let Some(GlassesInfo::Connected { entity, .. }) = list.glasses.get(&id) else {
+            continue;
+        };
+        let texture = Image::new(
+            Extent3d {
+                width: image.width,
+                height: image.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            image.pixels.clone(),
+            TextureFormat::R8Unorm,
+        );
+
+        let existing = children
+            .get(*entity)
+            .into_iter()
+            .flat_map(|children| children.iter())
+            .find(|child| matches!(frames.get(**child), Ok(frame) if frame.camera == camera));
+        match existing {
+            Some(child) => {
+                let Ok(mut frame) = frames.get_mut(*child) else {
+                    continue;
                 };
-                let rpos = T5_Vec3 {
-                    x: rpos.x,
-                    y: rpos.y,
-                    z: rpos.z,
+                frame.image = assets.set(frame.image.clone(), texture);
+                frame.illumination = image.illumination;
+                frame.position = image.position;
+                frame.rotation = image.rotation;
+            }
+            None => {
+                let frame = GlassesCameraFrame {
+                    glasses: id.clone(),
+                    camera,
+                    image: assets.add(texture),
+                    illumination: image.illumination,
+                    position: image.position,
+                    rotation: image.rotation,
                 };
+                commands.entity(*entity).with_children(|parent| {
+                    parent.spawn(frame);
+                });
+            }
+        }
+    }
+}
+
+/// Let the worker know whether any spectator cameras need the spectator pose,
+/// so it's only polled while they do
+fn track_spectators(
+    client: NonSend<T5ClientMainApp>,
+    cameras: Query<(), With<TiltFiveSpectatorCamera>>,
+    mut tracking: Local<bool>,
+) {
+    let track = !cameras.is_empty();
+    if track != *tracking {
+        *tracking = track;
+        let _ = client.sender.send(WorkerMessage::TrackSpectators(track));
+    }
+}
 
-                value.3 = Some((lpos, rpos, pose.rotToGLS_GBD));
+fn update_spectator_cameras(
+    mut events: EventReader<TiltFiveClientEvent>,
+    boards: Query<&GlobalTransform, With<BoardTransformer>>,
+    mut cameras: Query<(&TiltFiveSpectatorCamera, &mut Transform)>,
+) {
+    let Ok(board) = boards.get_single() else {
+        return;
+    };
+    let board = board.compute_transform();
+    for event in events.iter() {
+        if let TiltFiveClientEvent::SpectatorPoseChanged(id, pose) = event {
+            for (camera, mut transform) in cameras.iter_mut() {
+                if &camera.glasses == id {
+                    // Facing the same way as the eye cameras under the glasses
+                    *transform =
+                        board * *pose * Transform::from_rotation(Quat::from_rotation_x(PI));
+                }
             }
-            _ => bevy::log::error!("Couldn't get pose"),
         }
     }
 }
 
-fn set_glasses_position(
+fn update_wands(
     mut commands: Commands,
+    mut events: EventReader<TiltFiveClientEvent>,
+    boards: Query<Entity, With<BoardTransformer>>,
+    mut wands: Query<(Entity, &TiltFiveWand, &mut Transform, &mut PoseHistory)>,
+    mut pending: ResMut<PendingWands>,
+    settings: Res<TiltFiveSettings>,
+) {
+    for event in events.iter() {
+        match event {
+            TiltFiveClientEvent::WandConnected { glasses, wand_id } => {
+                pending.0.insert((glasses.clone(), wand_id.clone()));
+            }
+            TiltFiveClientEvent::WantReportUpdated {
+                glasses,
+                wand_id,
+                report:
+                    WandReport {
+                        pose: Some(pose),
+                        timestamp,
+                        ..
+                    },
+            } => {
+                let pose = pose.transform();
+                for (_, wand, mut transform, mut history) in wands.iter_mut() {
+                    if &wand.glasses == glasses && &wand.wand_id == wand_id {
+                        *transform = pose;
+                        history.push(*timestamp, pose);
+                    }
+                }
+            }
+            TiltFiveClientEvent::WandDisconnected { glasses, wand_id } => {
+                pending.0.remove(&(glasses.clone(), wand_id.clone()));
+                for (entity, wand, _, _) in wands.iter() {
+                    if &wand.glasses == glasses && &wand.wand_id == wand_id {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+            TiltFiveClientEvent::GlassesDisconnected(glasses) => {
+                pending.0.retain(|(id, _)| id != glasses);
+                for (entity, wand, _, _) in wands.iter() {
+                    if &wand.glasses == glasses {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Wands wait until there's a board to spawn them under
+    let Ok(board) = boards.get_single() else {
+        return;
+    };
+    for (glasses, wand_id) in pending.0.drain() {
+        if wands
+            .iter()
+            .any(|(_, wand, _, _)| wand.glasses == glasses && wand.wand_id == wand_id)
+        {
+            continue;
+        }
+        let wand = commands
+            .spawn((
+                SpatialBundle::default(),
+                TiltFiveWand { glasses, wand_id },
+                PoseHistory::new(settings.pose_history),
+            ))
+            .id();
+        commands.entity(board).add_child(wand);
+    }
+}
+
+/// Puts the gameboard each pair of glasses sees on the board they're under.
+/// Glasses only join their board once they're set up to render, so a gameboard
+/// that arrives before then waits for them.
+fn update_gameboard(
+    mut commands: Commands,
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut pending: Local<HashMap<Glasses, Gameboard>>,
     list: Res<AvailableGlasses>,
+    parents: Query<&Parent>,
+    boards: Query<(), With<Board>>,
+) {
+    for event in events.iter() {
+        match event {
+            TiltFiveClientEvent::GameboardChanged(id, gameboard) => {
+                pending.insert(id.clone(), *gameboard);
+            }
+            TiltFiveClientEvent::GlassesDisconnected(id) => {
+                pending.remove(id);
+            }
+            _ => {}
+        }
+    }
+    pending.retain(|id, gameboard| {
+        let Some(GlassesInfo::Connected { entity, .. }) = list.glasses.get(id) else {
+            return true;
+        };
+        // Glasses sit under the board's transformer, which sits under the board
+        let board = parents
+            .get(*entity)
+            .and_then(|transformer| parents.get(transformer.get()))
+            .map(|board| board.get());
+        match board {
+            Ok(board) if boards.contains(board) => {
+                commands.entity(board).insert(*gameboard);
+                false
+            }
+            _ => true,
+        }
+    });
+}
+
+fn recover_glasses(
+    mut list: ResMut<AvailableGlasses>,
     mut events: EventReader<TiltFiveClientEvent>,
 ) {
     for event in events.iter() {
-        if let TiltFiveClientEvent::GlassesPoseChanged(id, transform, ipd, _) = event {
-            if let Some(GlassesInfo::Connected {
-                entity,
-                left: _,
-                right: _,
-                friendly_name: _,
-            }) = list.glasses.get(id)
-            {
-                commands
-                    .entity(*entity)
-                    .insert((*transform, TiltFiveIPD(*ipd)));
+        if let TiltFiveClientEvent::GlassesRecovered(id, name) = event {
+            if let Some(GlassesInfo::Connected { friendly_name, .. }) = list.glasses.get_mut(id) {
+                *friendly_name = name.clone();
             }
         }
     }
 }
 
+fn update_service(
+    mut service: ResMut<TiltFiveService>,
+    mut events: EventReader<TiltFiveClientEvent>,
+) {
+    for event in events.iter() {
+        if let TiltFiveClientEvent::ServiceChanged(changed) = event {
+            if changed.attention_required && !service.attention_required {
+                warn!("The Tilt Five control panel needs attention");
+            }
+            *service = changed.clone();
+        }
+    }
+}
+
 fn setup_debug_meshes(_commands: Commands, _query: Query<Entity, Added<TiltFiveGlasses>>) {
     // for entity in query.iter() {
     //     commands.entity(entity).with_children(|p| {
@@ -628,11 +1211,19 @@ struct BufferSender {
     pub sender: Sender<GlassesBufferInfo>,
 }
 
-fn setup_buffers_for_frame(mut glasses: ResMut<T5RenderGlassesList>, device: Res<RenderDevice>) {
-    let fmt = TEXTURE_FORMAT.describe();
-    let bytes_per_row =
-        DEFAULT_GLASSES_WIDTH * (fmt.block_dimensions.0 as u32) * (fmt.block_size as u32);
-    let padded_bytes_total: u64 = bytes_per_row as u64 * (DEFAULT_GLASSES_HEIGHT as u64);
+fn setup_buffers_for_frame(
+    mut glasses: ResMut<T5RenderGlassesList>,
+    device: Res<RenderDevice>,
+    settings: Res<TiltFiveSettings>,
+    buffer_sender: Option<NonSend<BufferSender>>,
+) {
+    // Only a graphics interface that sends frames to the glasses needs them
+    // read back from the GPU - the simulator doesn't
+    if buffer_sender.is_none() {
+        return;
+    }
+    let padded_bytes_total: u64 =
+        settings.bytes_per_row() as u64 * (settings.render_resolution.y as u64);
     for (_, mut val) in glasses.glasses.iter_mut() {
         if val.1.is_some() {
             let left_buffer = device.create_buffer(&BufferDescriptor {
@@ -657,8 +1248,11 @@ const FRAME_DURATION: Duration = Duration::from_millis(16);
 fn retrieve_textures_from_gpu(
     glasses: Res<T5RenderGlassesList>,
     device: Res<RenderDevice>,
-    buffer_sender: NonSendMut<BufferSender>,
+    buffer_sender: Option<NonSendMut<BufferSender>>,
 ) {
+    let Some(buffer_sender) = buffer_sender else {
+        return;
+    };
     for (_, (glasses, _images, buffers, transform)) in glasses.glasses.iter() {
         if let (Some((lb, rb)), Some((lpos, rpos, rot))) = (buffers, transform) {
             let ls = lb.slice(..);
@@ -693,3 +1287,258 @@ fn retrieve_textures_from_gpu(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{f32::consts::PI, time::Duration};
+
+    use bevy::render::render_resource::TextureFormat;
+
+    use crate::{
+        backend::{client_events, connect, connected_app, connected_entity, run_frames, test_app},
+        bridge::ffi::T5_WandReport,
+    };
+
+    use super::*;
+
+    #[test]
+    fn connects_and_tracks_fake_glasses() {
+        let (mut app, backend, glasses, entity) = connected_app("fake-glasses");
+        assert!(backend.is_connected(&glasses));
+
+        backend.set_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        run_frames(&mut app);
+        let transform = app.world.get::<Transform>(entity).unwrap();
+        assert!((transform.translation - Vec3::new(0., 1., 0.)).length() < 1e-4);
+
+        app.world
+            .send_event(TiltFiveCommands::DisconnectFromGlasses(glasses.clone()));
+        run_frames(&mut app);
+        assert!(!backend.is_connected(&glasses));
+        assert!(connected_entity(&app, &glasses).is_none());
+        assert!(app.world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn publishes_camera_frames_as_images() {
+        let (mut app, backend, glasses, entity) = connected_app("camera-glasses");
+        app.world.send_event(TiltFiveCommands::SetCameraStream(
+            glasses.clone(),
+            GlassesCamera::Tangible,
+            true,
+        ));
+        run_frames(&mut app);
+        assert_eq!(
+            backend.with_glasses(&glasses, |g| g.camera_streams.clone()),
+            Some(vec![GlassesCamera::Tangible])
+        );
+
+        for illumination in [IlluminationMode::Light, IlluminationMode::Dark] {
+            backend.push_camera_image(
+                &glasses,
+                CameraImage {
+                    camera: GlassesCamera::Tangible,
+                    illumination,
+                    width: 2,
+                    height: 1,
+                    pixels: vec![10, 200],
+                    position: Vec3::new(0., 0., 0.5),
+                    rotation: Quat::IDENTITY,
+                },
+            );
+        }
+        run_frames(&mut app);
+
+        let mut frames = app.world.query::<(&GlassesCameraFrame, &Parent)>();
+        let (frame, parent) = frames.single(&app.world);
+        assert_eq!(parent.get(), entity);
+        assert_eq!(frame.camera, GlassesCamera::Tangible);
+        // Only the latest frame is kept
+        assert_eq!(frame.illumination, IlluminationMode::Dark);
+        assert_eq!(frame.position, Vec3::new(0., 0., 0.5));
+        let image = app
+            .world
+            .resource::<Assets<Image>>()
+            .get(&frame.image)
+            .unwrap();
+        assert_eq!(image.data, vec![10, 200]);
+        assert_eq!(image.texture_descriptor.format, TextureFormat::R8Unorm);
+    }
+
+    #[test]
+    fn tracks_the_board_the_glasses_see() {
+        let (mut app, backend, glasses, _) = connected_app("board-glasses");
+        backend.set_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        run_frames(&mut app);
+
+        let board = app
+            .world
+            .query_filtered::<Entity, With<Board>>()
+            .single(&app.world);
+        let mut boards = app.world.query::<&Gameboard>();
+        let gameboard = boards.get(&app.world, board).unwrap();
+        assert_eq!(gameboard.board_type, T5GameboardType::LE);
+        assert_eq!(gameboard.min, Vec3::new(-0.35, 0., -0.35));
+        assert_eq!(gameboard.max, Vec3::new(0.35, 0., 0.35));
+
+        // A board the glasses aren't under keeps its own gameboard
+        let other_board = app.world.spawn(BoardBundle::default()).id();
+
+        backend.set_gameboard_type(&glasses, T5GameboardType::XeRaised);
+        let events = client_events(&mut app);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TiltFiveClientEvent::GameboardChanged(id, Gameboard {
+                board_type: T5GameboardType::XeRaised,
+                ..
+            }) if *id == glasses
+        )));
+        let gameboard = boards.get(&app.world, board).unwrap();
+        assert_eq!(gameboard.board_type, T5GameboardType::XeRaised);
+        assert!((gameboard.size() - Vec3::new(1.4, 0.5, 0.7)).length() < 1e-5);
+        assert_eq!(
+            boards.get(&app.world, other_board).unwrap(),
+            &Gameboard::default()
+        );
+    }
+
+    #[test]
+    fn drives_spectator_cameras_from_the_spectator_pose() {
+        let (mut app, backend, glasses, entity) = connected_app("spectated-glasses");
+        let camera = app
+            .world
+            .spawn((
+                Transform::default(),
+                TiltFiveSpectatorCamera {
+                    glasses: glasses.clone(),
+                },
+            ))
+            .id();
+        backend.set_pose(&glasses, Vec3::new(0.1, 0., 1.), Quat::IDENTITY);
+        backend.set_spectator_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        run_frames(&mut app);
+
+        // The camera follows the smoothed pose rather than the glasses
+        let glasses_transform = *app.world.get::<Transform>(entity).unwrap();
+        assert!((glasses_transform.translation - Vec3::new(0.1, 1., 0.)).length() < 1e-4);
+        let transform = app.world.get::<Transform>(camera).unwrap();
+        assert!((transform.translation - Vec3::new(0., 1., 0.)).length() < 1e-4);
+        // Looking down at the board, like the eye cameras
+        assert!((transform.forward() - Vec3::NEG_Y).length() < 1e-4);
+    }
+
+    #[test]
+    fn only_polls_the_spectator_pose_for_spectator_cameras() {
+        let (mut app, backend, glasses, _) = connected_app("unspectated-glasses");
+        backend.set_spectator_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        let spectator_poses = |events: Vec<TiltFiveClientEvent>| {
+            events
+                .iter()
+                .filter(|event| matches!(event, TiltFiveClientEvent::SpectatorPoseChanged(..)))
+                .count()
+        };
+        assert_eq!(spectator_poses(client_events(&mut app)), 0);
+
+        // The pose hasn't moved, but the new camera still gets it
+        let camera = app
+            .world
+            .spawn((
+                Transform::default(),
+                TiltFiveSpectatorCamera {
+                    glasses: glasses.clone(),
+                },
+            ))
+            .id();
+        assert_eq!(spectator_poses(client_events(&mut app)), 1);
+
+        app.world.despawn(camera);
+        run_frames(&mut app);
+        backend.set_spectator_pose(&glasses, Vec3::new(0., 0., 2.), Quat::IDENTITY);
+        assert_eq!(spectator_poses(client_events(&mut app)), 0);
+    }
+
+    #[test]
+    fn spawns_wands_under_the_board() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "wand-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        // Paired before the glasses connected, so only the wand list knows about it
+        backend.with_glasses(&glasses, |g| g.wands.push(2));
+        let mut app = test_app(backend.clone());
+        connect(&mut app, &glasses);
+
+        let board = app
+            .world
+            .query_filtered::<Entity, With<BoardTransformer>>()
+            .single(&app.world);
+        let mut wands = app.world.query::<(Entity, &TiltFiveWand, &Parent)>();
+        let (wand, state, parent) = wands.single(&app.world);
+        assert_eq!(state.glasses, glasses);
+        assert_eq!(state.wand_id, "2");
+        assert_eq!(parent.get(), board);
+
+        let report = T5_WandReport {
+            timestampNanos: 1_000_000,
+            poseValid: true,
+            posGrip_GBD: Vec3::new(0.1, 0.2, 0.3).into(),
+            rotToWND_GBD: Quat::from_rotation_x(PI / 2.).into(),
+            ..Default::default()
+        };
+        backend.push_wand_report(&glasses, 2, report);
+        run_frames(&mut app);
+        let transform = app.world.get::<Transform>(wand).unwrap();
+        assert!((transform.translation - Vec3::new(0.1, 0.3, -0.2)).length() < 1e-5);
+        // Held level, pointing away from the player
+        assert!((transform.forward() - Vec3::NEG_Z).length() < 1e-5);
+        let history = app.world.get::<PoseHistory>(wand).unwrap();
+        assert_eq!(
+            history.latest().unwrap().timestamp,
+            Duration::from_millis(1)
+        );
+
+        backend.connect_wand(&glasses, 3);
+        run_frames(&mut app);
+        assert_eq!(wands.iter(&app.world).count(), 2);
+
+        backend.disconnect_wand(&glasses, 2);
+        run_frames(&mut app);
+        let (_, state, _) = wands.single(&app.world);
+        assert_eq!(state.wand_id, "3");
+        assert!(app.world.get_entity(wand).is_none());
+
+        app.world
+            .send_event(TiltFiveCommands::DisconnectFromGlasses(glasses.clone()));
+        run_frames(&mut app);
+        assert_eq!(wands.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn spawns_wands_that_connect_before_the_board() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "early-wand-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        let mut app = test_app(backend.clone());
+        let board = app
+            .world
+            .query_filtered::<Entity, With<Board>>()
+            .single(&app.world);
+        app.world.despawn(board);
+        connect(&mut app, &glasses);
+
+        backend.connect_wand(&glasses, 4);
+        run_frames(&mut app);
+        let mut wands = app.world.query::<(&TiltFiveWand, &Parent)>();
+        assert_eq!(wands.iter(&app.world).count(), 0);
+
+        app.world.spawn(BoardBundle::default());
+        run_frames(&mut app);
+        let board = app
+            .world
+            .query_filtered::<Entity, With<BoardTransformer>>()
+            .single(&app.world);
+        let (state, parent) = wands.single(&app.world);
+        assert_eq!(state.glasses, glasses);
+        assert_eq!(state.wand_id, "4");
+        assert_eq!(parent.get(), board);
+    }
+}
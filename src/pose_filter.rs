@@ -0,0 +1,351 @@
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{PoseHistory, TimedPose};
+
+/// How a `FilteredPose` smooths the poses it follows
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq)]
+pub enum PoseFilter {
+    /// Follow the raw pose - only predicting ahead, if asked to
+    None,
+    /// Ease towards each new pose, getting most of the way there in
+    /// `time_constant` seconds
+    Exponential { time_constant: f32 },
+    /// The 1€ filter - heavy smoothing while still, less as the pose speeds up
+    /// so it doesn't lag behind quick movements. `min_cutoff` is in Hz and
+    /// sets how steady it is at rest, `beta` how quickly smoothing falls off
+    /// with speed.
+    OneEuro {
+        min_cutoff: f32,
+        beta: f32,
+        derivative_cutoff: f32,
+    },
+    /// A constant velocity Kalman filter - `process_noise` is how erratically
+    /// the pose is expected to accelerate, `measurement_noise` how much the
+    /// tracking jitters, in meters
+    Kalman {
+        process_noise: f32,
+        measurement_noise: f32,
+    },
+}
+
+impl PoseFilter {
+    pub fn exponential() -> Self {
+        Self::Exponential {
+            time_constant: 0.05,
+        }
+    }
+
+    pub fn one_euro() -> Self {
+        Self::OneEuro {
+            min_cutoff: 1.,
+            beta: 5.,
+            derivative_cutoff: 1.,
+        }
+    }
+
+    pub fn kalman() -> Self {
+        Self::Kalman {
+            process_noise: 1.,
+            measurement_noise: 0.002,
+        }
+    }
+}
+
+impl Default for PoseFilter {
+    fn default() -> Self {
+        Self::one_euro()
+    }
+}
+
+/// A smoothed, predicted copy of the pose of glasses or a wand, for anything
+/// that wants a steadier pose than the one the glasses are rendered with -
+/// spectator views, gameplay, UI anchored to the player.
+///
+/// Each consumer gets its own filter, following the `PoseHistory` of
+/// `source`. The glasses themselves keep rendering from the raw pose.
+#[derive(Component, Reflect, FromReflect, Debug, Clone)]
+pub struct FilteredPose {
+    /// The glasses or wand entity to follow
+    pub source: Entity,
+    pub filter: PoseFilter,
+    /// How far past the latest pose to predict, from how fast it's moving
+    pub prediction: Duration,
+    /// Copy the result into this entity's `Transform` - it should share a
+    /// parent with `source` for that to line up
+    pub drive_transform: bool,
+    /// The filtered pose, in the space of the source's parent
+    pub transform: Transform,
+    #[reflect(ignore)]
+    state: FilterState,
+}
+
+impl FilteredPose {
+    pub fn new(source: Entity, filter: PoseFilter) -> Self {
+        Self {
+            source,
+            filter,
+            prediction: Duration::ZERO,
+            drive_transform: false,
+            transform: Transform::default(),
+            state: FilterState::default(),
+        }
+    }
+
+    pub fn with_prediction(mut self, prediction: Duration) -> Self {
+        self.prediction = prediction;
+        self
+    }
+
+    pub fn driving_transform(mut self) -> Self {
+        self.drive_transform = true;
+        self
+    }
+
+    /// Run every pose newer than the last one filtered through the filter, and
+    /// predict ahead from the result
+    pub fn update(&mut self, history: &PoseHistory) {
+        let Some(latest) = history.latest() else {
+            return;
+        };
+        if self.state.last == Some(latest.timestamp) {
+            return;
+        }
+        for pose in history.poses() {
+            if self.state.last.is_none_or(|last| pose.timestamp > last) {
+                self.state.step(&self.filter, pose);
+            }
+        }
+
+        let ahead = self.prediction.as_secs_f32();
+        self.transform = Transform {
+            translation: self.state.translation + history.linear_velocity() * ahead,
+            rotation: Quat::from_scaled_axis(history.angular_velocity() * ahead)
+                * self.state.rotation,
+            scale: latest.transform.scale,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct FilterState {
+    /// The timestamp of the last pose filtered
+    last: Option<Duration>,
+    translation: Vec3,
+    rotation: Quat,
+    /// The smoothed speed of the translation and rotation, for the 1€ filter
+    speed: f32,
+    angular_speed: f32,
+    /// The Kalman filter's velocity estimate, and its covariance per axis
+    velocity: Vec3,
+    position_variance: Vec3,
+    covariance: Vec3,
+    velocity_variance: Vec3,
+}
+
+impl FilterState {
+    fn step(&mut self, filter: &PoseFilter, pose: &TimedPose) {
+        let measured = pose.transform;
+        let Some(last) = self.last.replace(pose.timestamp) else {
+            self.translation = measured.translation;
+            self.rotation = measured.rotation;
+            self.position_variance = Vec3::ONE;
+            self.velocity_variance = Vec3::ONE;
+            return;
+        };
+        let dt = (pose.timestamp - last).as_secs_f32();
+
+        match *filter {
+            PoseFilter::None => {
+                self.translation = measured.translation;
+                self.rotation = measured.rotation;
+            }
+            PoseFilter::Exponential { time_constant } => {
+                let alpha = 1. - (-dt / time_constant.max(f32::EPSILON)).exp();
+                self.ease(measured, alpha, alpha);
+            }
+            PoseFilter::OneEuro {
+                min_cutoff,
+                beta,
+                derivative_cutoff,
+            } => {
+                let derivative_alpha = smoothing_factor(derivative_cutoff, dt);
+                let speed = (measured.translation - self.translation).length() / dt;
+                self.speed += (speed - self.speed) * derivative_alpha;
+                let angular_speed = self.rotation.angle_between(measured.rotation) / dt;
+                self.angular_speed += (angular_speed - self.angular_speed) * derivative_alpha;
+
+                let alpha = smoothing_factor(min_cutoff + beta * self.speed, dt);
+                let angular_alpha = smoothing_factor(min_cutoff + beta * self.angular_speed, dt);
+                self.ease(measured, alpha, angular_alpha);
+            }
+            PoseFilter::Kalman {
+                process_noise,
+                measurement_noise,
+            } => {
+                // Predict, with white noise acceleration
+                self.translation += self.velocity * dt;
+                self.position_variance += dt * (2. * self.covariance + dt * self.velocity_variance)
+                    + Vec3::splat(process_noise * dt.powi(3) / 3.);
+                self.covariance +=
+                    dt * self.velocity_variance + Vec3::splat(process_noise * dt.powi(2) / 2.);
+                self.velocity_variance += Vec3::splat(process_noise * dt);
+
+                // Correct towards the measurement
+                let innovation = measured.translation - self.translation;
+                let total = self.position_variance + Vec3::splat(measurement_noise.powi(2));
+                let position_gain = self.position_variance / total;
+                let velocity_gain = self.covariance / total;
+                self.translation += position_gain * innovation;
+                self.velocity += velocity_gain * innovation;
+                self.velocity_variance -= velocity_gain * self.covariance;
+                self.position_variance *= Vec3::ONE - position_gain;
+                self.covariance *= Vec3::ONE - position_gain;
+
+                // Rotations get the same trust in the measurement as positions
+                let gain = (position_gain.x + position_gain.y + position_gain.z) / 3.;
+                self.rotation = self.rotation.slerp(measured.rotation, gain);
+            }
+        }
+    }
+
+    fn ease(&mut self, measured: Transform, alpha: f32, angular_alpha: f32) {
+        self.translation = self.translation.lerp(measured.translation, alpha);
+        self.rotation = self.rotation.slerp(measured.rotation, angular_alpha);
+    }
+}
+
+/// How far the 1€ filter moves towards a new sample, for a cutoff in Hz
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1. / (2. * PI * cutoff.max(f32::EPSILON));
+    1. / (1. + tau / dt)
+}
+
+pub(crate) fn filter_poses(
+    histories: Query<&PoseHistory>,
+    mut filtered: Query<(&mut FilteredPose, Option<&mut Transform>)>,
+) {
+    for (mut pose, transform) in filtered.iter_mut() {
+        let Ok(history) = histories.get(pose.source) else {
+            continue;
+        };
+        pose.update(history);
+        if let (true, Some(mut transform)) = (pose.drive_transform, transform) {
+            if *transform != pose.transform {
+                *transform = pose.transform;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Moving along x at a meter per second, sampled every 5ms, with a
+    /// millimeter of jitter that alternates direction
+    fn jittery_history(samples: u64) -> PoseHistory {
+        let mut history = PoseHistory::new(Duration::from_secs(10));
+        for i in 0..samples {
+            let jitter = if i % 2 == 0 { 0.001 } else { -0.001 };
+            history.push(
+                Duration::from_millis(i * 5),
+                Transform::from_xyz(i as f32 * 0.005, jitter, 0.),
+            );
+        }
+        history
+    }
+
+    /// How far the filtered pose is off the line the jitter is around, over
+    /// the last few poses
+    fn jitter_after(filter: PoseFilter) -> f32 {
+        let mut pose = FilteredPose::new(Entity::from_raw(0), filter);
+        let mut history = PoseHistory::new(Duration::from_secs(10));
+        let full = jittery_history(200);
+        let mut worst: f32 = 0.;
+        for sample in full.poses() {
+            history.push(sample.timestamp, sample.transform);
+            pose.update(&history);
+            if sample.timestamp > Duration::from_millis(800) {
+                worst = worst.max(pose.transform.translation.y.abs());
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn filters_smooth_out_jitter() {
+        assert!((jitter_after(PoseFilter::None) - 0.001).abs() < 1e-6);
+        for filter in [
+            PoseFilter::exponential(),
+            PoseFilter::one_euro(),
+            PoseFilter::kalman(),
+        ] {
+            let jitter = jitter_after(filter);
+            assert!(jitter < 0.0006, "{filter:?} left {jitter}m of jitter");
+        }
+    }
+
+    #[test]
+    fn filters_keep_up_with_steady_movement() {
+        let history = jittery_history(200);
+        let latest = history.latest().unwrap().transform.translation.x;
+        for (filter, lag) in [
+            (PoseFilter::exponential(), 0.06),
+            (PoseFilter::one_euro(), 0.02),
+            (PoseFilter::kalman(), 0.005),
+        ] {
+            let mut pose = FilteredPose::new(Entity::from_raw(0), filter);
+            pose.update(&history);
+            let behind = latest - pose.transform.translation.x;
+            assert!(behind.abs() < lag, "{filter:?} was {behind}m behind");
+        }
+    }
+
+    #[test]
+    fn predicts_ahead_of_the_latest_pose() {
+        let history = jittery_history(20);
+        let mut pose = FilteredPose::new(Entity::from_raw(0), PoseFilter::None)
+            .with_prediction(Duration::from_millis(100));
+        pose.update(&history);
+        let latest = history.latest().unwrap().transform.translation;
+        assert!((pose.transform.translation.x - (latest.x + 0.1)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn drives_the_transform_of_consumers() {
+        let mut app = App::new();
+        app.add_system(filter_poses);
+        let mut history = PoseHistory::new(Duration::from_secs(1));
+        history.push(Duration::ZERO, Transform::from_xyz(1., 2., 3.));
+        let source = app.world.spawn(history).id();
+        let driven = app
+            .world
+            .spawn((
+                Transform::default(),
+                FilteredPose::new(source, PoseFilter::default()).driving_transform(),
+            ))
+            .id();
+        let read_only = app
+            .world
+            .spawn((
+                Transform::default(),
+                FilteredPose::new(source, PoseFilter::default()),
+            ))
+            .id();
+        app.update();
+
+        let expected = Vec3::new(1., 2., 3.);
+        assert_eq!(
+            app.world.get::<Transform>(driven).unwrap().translation,
+            expected
+        );
+        assert_eq!(
+            app.world.get::<Transform>(read_only).unwrap().translation,
+            Vec3::ZERO
+        );
+        let filtered = app.world.get::<FilteredPose>(read_only).unwrap();
+        assert_eq!(filtered.transform.translation, expected);
+    }
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Velocities are estimated over the poses from this long before the latest,
+/// to smooth over jitter between consecutive poses
+const VELOCITY_WINDOW: Duration = Duration::from_millis(50);
+
+/// A pose as the SDK reported it, in the space of the entity's parent
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq)]
+pub struct TimedPose {
+    /// When the SDK took the pose, on its own clock
+    pub timestamp: Duration,
+    pub transform: Transform,
+}
+
+/// The recent poses of glasses or a wand, oldest first.
+///
+/// Poses older than `max_age` before the latest one are dropped. Timestamps
+/// are on the SDK's clock, so `sample_ago` is usually more convenient than
+/// `sample` for looking back in time.
+#[derive(Component, Reflect, FromReflect, Debug, Clone, Default)]
+#[reflect(Component)]
+pub struct PoseHistory {
+    pub max_age: Duration,
+    poses: Vec<TimedPose>,
+}
+
+impl PoseHistory {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            poses: vec![],
+        }
+    }
+
+    /// Add a pose - ones no newer than the latest pose are ignored
+    pub fn push(&mut self, timestamp: Duration, transform: Transform) {
+        if self
+            .latest()
+            .is_some_and(|latest| timestamp <= latest.timestamp)
+        {
+            return;
+        }
+        self.poses.push(TimedPose {
+            timestamp,
+            transform,
+        });
+        let oldest = timestamp.saturating_sub(self.max_age);
+        let expired = self.poses.partition_point(|pose| pose.timestamp < oldest);
+        self.poses.drain(..expired);
+    }
+
+    pub fn poses(&self) -> &[TimedPose] {
+        &self.poses
+    }
+
+    pub fn latest(&self) -> Option<&TimedPose> {
+        self.poses.last()
+    }
+
+    /// The pose at `timestamp`, interpolated between the poses either side of
+    /// it. Times before the oldest pose give `None`, and times after the
+    /// latest give the latest pose.
+    pub fn sample(&self, timestamp: Duration) -> Option<Transform> {
+        let after = self
+            .poses
+            .partition_point(|pose| pose.timestamp < timestamp);
+        if after == self.poses.len() {
+            return self.latest().map(|pose| pose.transform);
+        }
+        let next = &self.poses[after];
+        if next.timestamp == timestamp {
+            return Some(next.transform);
+        }
+        let previous = &self.poses[after.checked_sub(1)?];
+        let t = (timestamp - previous.timestamp).as_secs_f32()
+            / (next.timestamp - previous.timestamp).as_secs_f32();
+        let (from, to) = (previous.transform, next.transform);
+        Some(Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            scale: from.scale.lerp(to.scale, t),
+        })
+    }
+
+    /// The pose `ago` before the latest one
+    pub fn sample_ago(&self, ago: Duration) -> Option<Transform> {
+        let latest = self.latest()?.timestamp;
+        self.sample(latest.checked_sub(ago)?)
+    }
+
+    /// How fast the pose is moving, in meters per second in the parent's space
+    pub fn linear_velocity(&self) -> Vec3 {
+        let Some((from, to, seconds)) = self.velocity_window() else {
+            return Vec3::ZERO;
+        };
+        (to.translation - from.translation) / seconds
+    }
+
+    /// How fast the pose is turning, as an axis in the parent's space scaled
+    /// by radians per second
+    pub fn angular_velocity(&self) -> Vec3 {
+        let Some((from, to, seconds)) = self.velocity_window() else {
+            return Vec3::ZERO;
+        };
+        let mut delta = to.rotation * from.rotation.inverse();
+        // Take the short way round
+        if delta.w < 0. {
+            delta = -delta;
+        }
+        let (axis, angle) = delta.to_axis_angle();
+        axis * angle / seconds
+    }
+
+    /// The oldest pose in the velocity window and the latest one, with the
+    /// seconds between them - falling back to the pose before the latest when
+    /// nothing else is that recent
+    fn velocity_window(&self) -> Option<(Transform, Transform, f32)> {
+        let latest = self.poses.len().checked_sub(1)?;
+        let start = self.poses[latest].timestamp.saturating_sub(VELOCITY_WINDOW);
+        let oldest = self
+            .poses
+            .partition_point(|pose| pose.timestamp < start)
+            .min(latest.checked_sub(1)?);
+        let (from, to) = (&self.poses[oldest], &self.poses[latest]);
+        let seconds = (to.timestamp - from.timestamp).as_secs_f32();
+        Some((from.transform, to.transform, seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::backend::{connected_app, run_frames};
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// Moving along x at a meter per second, and turning a quarter turn a
+    /// second about y
+    fn moving_history() -> PoseHistory {
+        let mut history = PoseHistory::new(ms(100));
+        for t in 0..=20 {
+            let seconds = t as f32 * 0.01;
+            history.push(
+                ms(t * 10),
+                Transform::from_xyz(seconds, 0., 0.)
+                    .with_rotation(Quat::from_rotation_y(seconds * PI / 2.)),
+            );
+        }
+        history
+    }
+
+    #[test]
+    fn keeps_recent_poses_in_order() {
+        let mut history = moving_history();
+        assert_eq!(history.poses().len(), 11);
+        assert_eq!(history.poses()[0].timestamp, ms(100));
+        assert_eq!(history.latest().unwrap().timestamp, ms(200));
+
+        // Poses arriving out of order are dropped
+        history.push(ms(150), Transform::default());
+        assert_eq!(history.poses().len(), 11);
+        assert_eq!(history.latest().unwrap().timestamp, ms(200));
+    }
+
+    #[test]
+    fn samples_between_poses() {
+        let history = moving_history();
+        let sampled = history.sample(ms(155)).unwrap();
+        assert!((sampled.translation.x - 0.155).abs() < 1e-5);
+        let sampled = history.sample_ago(ms(20)).unwrap();
+        assert!((sampled.translation.x - 0.18).abs() < 1e-5);
+        assert!(
+            sampled
+                .rotation
+                .angle_between(Quat::from_rotation_y(0.18 * PI / 2.))
+                < 1e-3
+        );
+
+        // Too old to have been kept
+        assert_eq!(history.sample(ms(50)), None);
+        assert_eq!(history.sample_ago(ms(500)), None);
+        // Newer than anything seen yet
+        assert_eq!(
+            history.sample(ms(300)),
+            Some(history.latest().unwrap().transform)
+        );
+    }
+
+    #[test]
+    fn estimates_velocity() {
+        let history = moving_history();
+        assert!((history.linear_velocity() - Vec3::X).length() < 1e-3);
+        assert!((history.angular_velocity() - Vec3::Y * PI / 2.).length() < 1e-3);
+
+        let mut still = PoseHistory::new(ms(100));
+        assert_eq!(still.linear_velocity(), Vec3::ZERO);
+        still.push(ms(0), Transform::default());
+        assert_eq!(still.angular_velocity(), Vec3::ZERO);
+        // Poses further apart than the window still give a velocity
+        still.push(ms(90), Transform::from_xyz(0., 0.09, 0.));
+        assert!((still.linear_velocity() - Vec3::Y).length() < 1e-3);
+    }
+
+    #[test]
+    fn keeps_a_history_of_glasses_poses() {
+        let (mut app, backend, glasses, entity) = connected_app("history-glasses");
+
+        for (timestamp, height) in [(1_000_000_000, 1.), (1_010_000_000, 1.01)] {
+            backend.set_pose(&glasses, Vec3::new(0., 0., height), Quat::IDENTITY);
+            backend.with_glasses(&glasses, |g| g.pose.timestampNanos = timestamp);
+            run_frames(&mut app);
+        }
+
+        let history = app.world.get::<PoseHistory>(entity).unwrap();
+        assert_eq!(history.poses().len(), 2);
+        let latest = history.latest().unwrap();
+        assert_eq!(latest.timestamp, Duration::from_millis(1010));
+        assert_eq!(
+            &latest.transform,
+            app.world.get::<Transform>(entity).unwrap()
+        );
+        // Rising off the board at a meter a second
+        assert!((history.linear_velocity() - Vec3::Y).length() < 1e-3);
+    }
+}
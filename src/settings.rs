@@ -0,0 +1,179 @@
+use std::{path::PathBuf, time::Duration};
+
+use bevy::{
+    prelude::*,
+    reflect::{FromReflect, Reflect},
+    render::{render_resource::Extent3d, renderer::RenderDevice},
+};
+
+use crate::bridge::{
+    ffi::{
+        T5_GlassesPoseUsage, T5_GlassesPoseUsage_kT5_GlassesPoseUsage_GlassesPresentation,
+        T5_GlassesPoseUsage_kT5_GlassesPoseUsage_SpectatorPresentation,
+    },
+    Glasses, DEFAULT_GLASSES_HEIGHT, DEFAULT_GLASSES_WIDTH,
+};
+use crate::TEXTURE_FORMAT;
+
+/// How the plugin identifies itself to the Tilt Five service, and how it
+/// talks to the glasses.
+///
+/// `TiltFivePlugin` inserts this as a resource. It's read when the plugin is
+/// built, so changing the resource afterwards has no effect.
+#[derive(Resource, Reflect, FromReflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct TiltFiveSettings {
+    /// Identifies the app in the Tilt Five control panel
+    pub application_id: String,
+    pub application_version: String,
+    /// The name glasses show while this app has them reserved - `{app}` and
+    /// `{glasses}` are replaced by the application id and the glasses id.
+    /// `TiltFiveCommands::SetGlassesDisplayName` overrides it for particular glasses
+    pub display_name_template: String,
+    pub pose_usage: GlassesPoseUsage,
+    pub retry_policy: RetryPolicy,
+    /// Give up on connecting to glasses that aren't ready after this long
+    pub connection_timeout: Duration,
+    /// How to get glasses back after they're unplugged - `None` disconnects
+    /// them instead
+    pub reconnect_policy: Option<ReconnectPolicy>,
+    /// The size of the image rendered for each eye, in pixels
+    pub render_resolution: UVec2,
+    /// Load the native library from here, instead of searching for it
+    pub library_path: Option<PathBuf>,
+    /// How many image buffers each camera stream keeps with the service -
+    /// more buffers drop fewer frames when the app falls behind
+    pub camera_buffers: usize,
+    /// How far back the `PoseHistory` of glasses and wands goes
+    pub pose_history: Duration,
+    /// How often the worker polls the service for poses, wand events and
+    /// connection changes. Draining the wand stream waits up to a millisecond
+    /// for each pair of glasses, so with several connected a poll can outlast
+    /// this - the worker then polls back to back, keeping its thread busy
+    pub poll_interval: Duration,
+}
+
+impl Default for TiltFiveSettings {
+    fn default() -> Self {
+        let application_id = std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.file_stem()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "bevy-tilt-five".to_string());
+        Self {
+            application_id,
+            application_version: "1".to_string(),
+            display_name_template: "{app} - {glasses}".to_string(),
+            pose_usage: Default::default(),
+            retry_policy: Default::default(),
+            connection_timeout: Duration::from_secs(30),
+            reconnect_policy: Some(Default::default()),
+            render_resolution: UVec2::new(DEFAULT_GLASSES_WIDTH, DEFAULT_GLASSES_HEIGHT),
+            library_path: None,
+            camera_buffers: 3,
+            pose_history: Duration::from_secs(1),
+            poll_interval: Duration::from_millis(2),
+        }
+    }
+}
+
+impl TiltFiveSettings {
+    /// The display name for the glasses, filled in from `display_name_template`
+    pub fn display_name(&self, glasses: &Glasses) -> String {
+        self.display_name_template
+            .replace("{app}", &self.application_id)
+            .replace("{glasses}", glasses.into())
+    }
+
+    /// The size of the texture rendered for each eye
+    pub fn texture_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.render_resolution.x,
+            height: self.render_resolution.y,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    /// How far apart the rows of an eye's image are once it's read back from
+    /// the GPU - padded out to the alignment wgpu copies rows at, so any
+    /// width works
+    pub fn bytes_per_row(&self) -> u32 {
+        let fmt = TEXTURE_FORMAT.describe();
+        let row = self.render_resolution.x * fmt.block_dimensions.0 as u32 * fmt.block_size as u32;
+        RenderDevice::align_copy_bytes_per_row(row as usize) as u32
+    }
+}
+
+/// What the glasses pose is going to be used for
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GlassesPoseUsage {
+    /// Rendering to the glasses themselves
+    #[default]
+    GlassesPresentation,
+    /// Rendering a spectator view on another display
+    SpectatorPresentation,
+}
+
+impl From<GlassesPoseUsage> for T5_GlassesPoseUsage {
+    fn from(value: GlassesPoseUsage) -> Self {
+        match value {
+            GlassesPoseUsage::GlassesPresentation => {
+                T5_GlassesPoseUsage_kT5_GlassesPoseUsage_GlassesPresentation
+            }
+            GlassesPoseUsage::SpectatorPresentation => {
+                T5_GlassesPoseUsage_kT5_GlassesPoseUsage_SpectatorPresentation
+            }
+        }
+    }
+}
+
+/// How long to keep trying while the Tilt Five service isn't available,
+/// when creating the context or connecting to glasses
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 100,
+            delay: Duration::from_millis(10),
+        }
+    }
+}
+
+/// How often to try reconnecting to glasses that were lost while connected
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub interval: Duration,
+    /// Give up and disconnect the glasses after this many failed attempts -
+    /// `None` keeps trying for as long as the app runs
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            max_attempts: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_rows_to_the_copy_alignment() {
+        let mut settings = TiltFiveSettings::default();
+        // 1216 pixels of 4 bytes happen to line up already
+        assert_eq!(settings.bytes_per_row(), 1216 * 4);
+        settings.render_resolution = UVec2::new(1000, 600);
+        assert_eq!(settings.bytes_per_row(), 4096);
+    }
+}
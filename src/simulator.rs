@@ -0,0 +1,497 @@
+use std::{
+    f32::consts::PI,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    window::{CreateWindow, WindowId},
+};
+
+use crate::{
+    bridge::ffi::{
+        T5_FrameInfo, T5_GameboardType_kT5_GameboardType_LE, T5_GlassesPose,
+        T5_Hand_kT5_Hand_Right, T5_Vec2, T5_WandHandle, T5_WandReport, T5_WandReport__bindgen_ty_1,
+        T5_WandStreamEvent, T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+        T5_WandStreamEventType_kT5_WandStreamEventType_Report,
+    },
+    conversions::glasses_pose_from_bevy_transform,
+    AvailableGlasses, ConnectionProgress, Glasses, GlassesConnectionState, GlassesInfo, T5Error,
+    T5Result, TiltFiveBackend, TiltFiveCommands, TiltFivePlugin, TiltFiveSettings,
+};
+
+/// The id the simulated glasses show up with in `AvailableGlasses`
+pub const SIMULATED_GLASSES_ID: &str = "simulated-glasses";
+
+const SIMULATED_WAND: T5_WandHandle = 1;
+const SIMULATED_IPD: f32 = 59.;
+const SIMULATED_NAME: &str = "Simulated Glasses";
+
+/// The render layer the eye preview is drawn on, so other 2d cameras don't see it
+const PREVIEW_LAYER: u8 = 31;
+
+/// Runs the plugin against a virtual pair of glasses and wand, for working on
+/// tabletop content without a headset. Use it instead of `TiltFivePlugin`.
+///
+/// - Hold the right mouse button and move the mouse to look around
+/// - `W`/`A`/`S`/`D` move across the board, `Q`/`E` move down and up
+/// - The left mouse button pulls the wand trigger, and the middle button presses its T5 button
+/// - The scroll wheel moves the wand closer or further away
+/// - `1`/`2`/`3` and `Z`/`X`/`C`/`V` press the wand's 1/2/3 and A/B/X/Y buttons,
+///   and the arrow keys move its stick
+pub struct TiltFiveSimulatorPlugin {
+    /// Show what each eye sees in a separate window
+    pub preview_window: bool,
+    pub settings: TiltFiveSettings,
+}
+
+impl Default for TiltFiveSimulatorPlugin {
+    fn default() -> Self {
+        Self {
+            preview_window: true,
+            settings: Default::default(),
+        }
+    }
+}
+
+impl Plugin for TiltFiveSimulatorPlugin {
+    fn build(&self, app: &mut App) {
+        let backend = SimulatorBackend::default();
+        let simulator = TiltFiveSimulator {
+            backend: backend.clone(),
+            ..Default::default()
+        };
+
+        let plugin =
+            TiltFivePlugin::with_backend(self.settings.clone(), move || Ok(backend.clone()));
+
+        app.add_plugin(plugin)
+            .insert_resource(simulator)
+            .add_startup_system(refresh_glasses_list)
+            .add_system(connect_simulated_glasses)
+            .add_system(drive_simulated_glasses);
+
+        if self.preview_window {
+            app.add_system(preview_simulated_eyes);
+        }
+    }
+}
+
+/// The state of the virtual glasses and wand, in the board's space.
+#[derive(Resource)]
+pub struct TiltFiveSimulator {
+    backend: SimulatorBackend,
+    pub position: Vec3,
+    /// Rotation around the board's up axis, in radians
+    pub yaw: f32,
+    /// In radians, negative looks down at the board
+    pub pitch: f32,
+    /// How far in front of the glasses the wand is held, in meters
+    pub wand_reach: f32,
+    /// In meters per second
+    pub move_speed: f32,
+    /// In radians per pixel of mouse movement
+    pub look_sensitivity: f32,
+}
+
+impl Default for TiltFiveSimulator {
+    fn default() -> Self {
+        Self {
+            backend: Default::default(),
+            position: Vec3::new(0., 0.5, 0.5),
+            yaw: 0.,
+            pitch: -PI / 4.,
+            wand_reach: 0.3,
+            move_speed: 0.5,
+            look_sensitivity: 0.003,
+        }
+    }
+}
+
+impl TiltFiveSimulator {
+    /// The rotation of a camera looking where the glasses are
+    pub fn view_rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.)
+    }
+
+    /// Where the glasses are on the board
+    pub fn glasses_transform(&self) -> Transform {
+        // The eye cameras render upside down for the glasses, so the glasses
+        // themselves are turned around to leave the cameras facing the view
+        Transform::from_translation(self.position)
+            .with_rotation(self.view_rotation() * Quat::from_rotation_y(PI))
+    }
+
+    /// Where the wand is on the board
+    pub fn wand_transform(&self) -> Transform {
+        let rotation = self.view_rotation();
+        Transform::from_translation(
+            self.position + rotation * Vec3::new(0.1, -0.15, -self.wand_reach),
+        )
+        .with_rotation(rotation)
+    }
+}
+
+#[derive(Clone, Default)]
+struct SimulatorBackend {
+    state: Arc<Mutex<SimulatorState>>,
+}
+
+#[derive(Default)]
+struct SimulatorState {
+    connected: bool,
+    pose: Option<T5_GlassesPose>,
+    wand_events: Vec<T5_WandStreamEvent>,
+}
+
+impl SimulatorBackend {
+    fn connected<R>(
+        &self,
+        glasses: &Glasses,
+        f: impl FnOnce(&mut SimulatorState) -> T5Result<R>,
+    ) -> T5Result<R> {
+        let mut state = self.state.lock().unwrap();
+        if glasses.to_string() != SIMULATED_GLASSES_ID {
+            Err(T5Error::GlassesNotFound(glasses.to_string()))
+        } else if !state.connected {
+            Err(T5Error::NotConnected)
+        } else {
+            f(&mut state)
+        }
+    }
+}
+
+impl TiltFiveBackend for SimulatorBackend {
+    fn list_glasses(&mut self) -> T5Result<Vec<Glasses>> {
+        Ok(vec![SIMULATED_GLASSES_ID.into()])
+    }
+
+    fn connect_glasses(&mut self, glasses: &Glasses) -> T5Result<ConnectionProgress> {
+        if glasses.to_string() != SIMULATED_GLASSES_ID {
+            return Err(T5Error::GlassesNotFound(glasses.to_string()));
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.connected {
+            state.connected = true;
+            let timestamp = state.pose.map(|pose| pose.timestampNanos);
+            state.wand_events.push(T5_WandStreamEvent {
+                wandId: SIMULATED_WAND,
+                type_: T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+                timestampNanos: timestamp.unwrap_or_default(),
+                report: T5_WandReport::default(),
+            });
+        }
+        Ok(ConnectionProgress::Ready)
+    }
+
+    fn release_glasses(&mut self, _glasses: Glasses) -> T5Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.connected = false;
+        state.wand_events.clear();
+        Ok(())
+    }
+
+    fn get_glasses_pose(&mut self, glasses: &Glasses) -> T5Result<T5_GlassesPose> {
+        self.connected(glasses, |state| state.pose.ok_or(T5Error::TryAgain))
+    }
+
+    fn get_connection_state(&mut self, glasses: &Glasses) -> T5Result<GlassesConnectionState> {
+        self.connected(glasses, |_| Ok(GlassesConnectionState::ExclusiveConnection))
+    }
+
+    fn get_ipd(&mut self, glasses: &Glasses) -> T5Result<f32> {
+        self.connected(glasses, |_| Ok(SIMULATED_IPD))
+    }
+
+    fn get_glasses_name(&mut self, glasses: &Glasses) -> T5Result<String> {
+        self.connected(glasses, |_| Ok(SIMULATED_NAME.to_string()))
+    }
+
+    fn get_wand_stream_events(&mut self, glasses: &Glasses) -> T5Result<Vec<T5_WandStreamEvent>> {
+        self.connected(glasses, |state| Ok(std::mem::take(&mut state.wand_events)))
+    }
+
+    unsafe fn send_frame_to_glasses(
+        &mut self,
+        glasses: &Glasses,
+        _info: *const T5_FrameInfo,
+    ) -> T5Result<()> {
+        self.connected(glasses, |_| Ok(()))
+    }
+}
+
+fn refresh_glasses_list(mut commands: EventWriter<TiltFiveCommands>) {
+    commands.send(TiltFiveCommands::RefreshGlassesList);
+}
+
+/// Connect to the simulated glasses once they're found - after that, they can
+/// be disconnected and reconnected like any other glasses
+fn connect_simulated_glasses(
+    mut connected: Local<bool>,
+    list: Res<AvailableGlasses>,
+    mut commands: EventWriter<TiltFiveCommands>,
+) {
+    if *connected {
+        return;
+    }
+    let glasses: Glasses = SIMULATED_GLASSES_ID.into();
+    if let Some(GlassesInfo::Disconnected) = list.glasses.get(&glasses) {
+        commands.send(TiltFiveCommands::ConnectToGlasses(glasses));
+        *connected = true;
+    }
+}
+
+fn drive_simulated_glasses(
+    mut simulator: ResMut<TiltFiveSimulator>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    time: Res<Time>,
+) {
+    if mouse.pressed(MouseButton::Right) {
+        for motion in mouse_motion.iter() {
+            simulator.yaw -= motion.delta.x * simulator.look_sensitivity;
+            simulator.pitch = (simulator.pitch - motion.delta.y * simulator.look_sensitivity)
+                .clamp(-PI / 2., PI / 2.);
+        }
+    } else {
+        mouse_motion.clear();
+    }
+
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        keys.pressed(positive) as i32 as f32 - keys.pressed(negative) as i32 as f32
+    };
+    let flat_rotation = Quat::from_rotation_y(simulator.yaw);
+    let movement = flat_rotation * Vec3::new(axis(KeyCode::A, KeyCode::D), 0., 0.)
+        + flat_rotation * Vec3::new(0., 0., axis(KeyCode::W, KeyCode::S))
+        + Vec3::new(0., axis(KeyCode::Q, KeyCode::E), 0.);
+    let step = movement.normalize_or_zero() * simulator.move_speed * time.delta_seconds();
+    simulator.position += step;
+
+    for wheel in mouse_wheel.iter() {
+        let scale = match wheel.unit {
+            MouseScrollUnit::Line => 0.02,
+            MouseScrollUnit::Pixel => 0.001,
+        };
+        simulator.wand_reach = (simulator.wand_reach + wheel.y * scale).clamp(0.1, 1.);
+    }
+
+    let timestamp = time.elapsed().as_nanos() as u64;
+    let (position, rotation) = glasses_pose_from_bevy_transform(&simulator.glasses_transform());
+    let (wand_position, wand_rotation) =
+        glasses_pose_from_bevy_transform(&simulator.wand_transform());
+    let (aim_position, _) = glasses_pose_from_bevy_transform(
+        &simulator
+            .wand_transform()
+            .mul_transform(Transform::from_xyz(0., 0., -0.1)),
+    );
+
+    let mut state = simulator.backend.state.lock().unwrap();
+    state.pose = Some(T5_GlassesPose {
+        timestampNanos: timestamp,
+        posGLS_GBD: position.into(),
+        rotToGLS_GBD: rotation.into(),
+        gameboardType: T5_GameboardType_kT5_GameboardType_LE,
+    });
+
+    if !state.connected {
+        return;
+    }
+    let report = T5_WandReport {
+        timestampNanos: timestamp,
+        analogValid: true,
+        batteryValid: true,
+        buttonsValid: true,
+        poseValid: true,
+        trigger: mouse.pressed(MouseButton::Left) as i32 as f32,
+        stick: T5_Vec2 {
+            x: axis(KeyCode::Left, KeyCode::Right),
+            y: axis(KeyCode::Down, KeyCode::Up),
+        },
+        battery: u8::MAX,
+        buttons: T5_WandReport__bindgen_ty_1 {
+            t5: mouse.pressed(MouseButton::Middle),
+            one: keys.pressed(KeyCode::Key1),
+            two: keys.pressed(KeyCode::Key2),
+            three: keys.pressed(KeyCode::Key3),
+            a: keys.pressed(KeyCode::Z),
+            b: keys.pressed(KeyCode::X),
+            x: keys.pressed(KeyCode::C),
+            y: keys.pressed(KeyCode::V),
+        },
+        rotToWND_GBD: wand_rotation.into(),
+        posAim_GBD: aim_position.into(),
+        posFingertips_GBD: wand_position.into(),
+        posGrip_GBD: wand_position.into(),
+        hand: T5_Hand_kT5_Hand_Right,
+    };
+    state.wand_events.push(T5_WandStreamEvent {
+        wandId: SIMULATED_WAND,
+        type_: T5_WandStreamEventType_kT5_WandStreamEventType_Report,
+        timestampNanos: timestamp,
+        report,
+    });
+}
+
+#[derive(Component)]
+struct SimulatorPreview;
+
+struct PreviewWindow {
+    showing: Option<Handle<Image>>,
+}
+
+/// Shows the eye cameras of the simulated glasses side by side in their own window
+fn preview_simulated_eyes(
+    mut commands: Commands,
+    list: Res<AvailableGlasses>,
+    create_window: Option<ResMut<Events<CreateWindow>>>,
+    mut preview: Local<Option<PreviewWindow>>,
+    sprites: Query<Entity, With<SimulatorPreview>>,
+    settings: Res<TiltFiveSettings>,
+) {
+    let resolution = settings.render_resolution.as_vec2();
+    let Some(mut create_window) = create_window else {
+        return;
+    };
+    if !list.is_changed() {
+        return;
+    }
+    let images = match list.glasses.get(&SIMULATED_GLASSES_ID.into()) {
+        Some(GlassesInfo::Connected { left, right, .. }) => Some((left.clone(), right.clone())),
+        _ => None,
+    };
+    let preview = preview.get_or_insert_with(|| {
+        let window = WindowId::new();
+        create_window.send(CreateWindow {
+            id: window,
+            descriptor: WindowDescriptor {
+                title: "Tilt Five Simulator".to_string(),
+                width: resolution.x,
+                height: resolution.y / 2.,
+                ..Default::default()
+            },
+        });
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    target: RenderTarget::Window(window),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderLayers::layer(PREVIEW_LAYER),
+        ));
+        PreviewWindow { showing: None }
+    });
+    if preview.showing.as_ref() == images.as_ref().map(|(left, _)| left) {
+        return;
+    }
+
+    for entity in sprites.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    preview.showing = images.as_ref().map(|(left, _)| left.clone());
+    let Some((left, right)) = images else {
+        return;
+    };
+    let size = resolution / 2.;
+    for (image, x) in [(left, -size.x / 2.), (right, size.x / 2.)] {
+        commands.spawn((
+            SpriteBundle {
+                texture: image,
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    // The glasses expect their frames upside down
+                    flip_y: true,
+                    ..Default::default()
+                },
+                transform: Transform::from_xyz(x, 0., 0.),
+                ..Default::default()
+            },
+            RenderLayers::layer(PREVIEW_LAYER),
+            SimulatorPreview,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::InputPlugin;
+
+    use crate::{backend::run_frames, BoardBundle, TiltFiveClientEvent, WandReport};
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct WandReports(Vec<WandReport>);
+
+    fn collect_wand_reports(
+        mut reports: ResMut<WandReports>,
+        mut events: EventReader<TiltFiveClientEvent>,
+    ) {
+        for event in events.iter() {
+            if let TiltFiveClientEvent::WantReportUpdated { report, .. } = event {
+                reports.0.push(*report);
+            }
+        }
+    }
+
+    #[test]
+    fn simulated_glasses_follow_the_controls() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(InputPlugin)
+            .add_asset::<Image>()
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_plugin(TiltFiveSimulatorPlugin::default())
+            .init_resource::<WandReports>()
+            .add_system(collect_wand_reports);
+        app.world.spawn(BoardBundle::default());
+
+        run_frames(&mut app);
+        run_frames(&mut app);
+        let entity = match app
+            .world
+            .resource::<AvailableGlasses>()
+            .glasses
+            .get(&SIMULATED_GLASSES_ID.into())
+        {
+            Some(GlassesInfo::Connected { entity, .. }) => *entity,
+            _ => panic!("simulated glasses should connect on their own"),
+        };
+
+        {
+            let mut simulator = app.world.resource_mut::<TiltFiveSimulator>();
+            simulator.position = Vec3::new(0.2, 0.4, 0.1);
+            simulator.yaw = 0.3;
+        }
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Left);
+        run_frames(&mut app);
+
+        let expected = app
+            .world
+            .resource::<TiltFiveSimulator>()
+            .glasses_transform();
+        let transform = app.world.get::<Transform>(entity).unwrap();
+        assert!((transform.translation - expected.translation).length() < 1e-4);
+        assert!(transform.rotation.angle_between(expected.rotation) < 1e-3);
+
+        let reports = &app.world.resource::<WandReports>().0;
+        assert_eq!(reports.last().unwrap().analog.unwrap().trigger, 1.);
+
+        let expected = app.world.resource::<TiltFiveSimulator>().wand_transform();
+        let mut wands = app
+            .world
+            .query_filtered::<&Transform, With<crate::TiltFiveWand>>();
+        let transform = wands.single(&app.world);
+        assert!((transform.translation - expected.translation).length() < 1e-4);
+        assert!(transform.rotation.angle_between(expected.rotation) < 1e-3);
+    }
+}
@@ -0,0 +1,474 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    conversions::bevy_position_from_gameboard, BoardTransformer, CameraImage, Glasses,
+    GlassesCamera, IlluminationMode, TiltFiveClientEvent, TiltFiveCommands,
+};
+
+/// Tracks retroreflective stickers on the board - game pieces with a sticker
+/// on top can drive virtual content.
+///
+/// The glasses light the board with IR on every other frame of the tangible
+/// tracking camera, so a sticker is bright in a lit frame and dark in the next
+/// one. Each pair of frames is thresholded on that difference, the bright
+/// blobs found, and a ray from the camera through each blob followed down to
+/// the board. Every tracked blob gets an entity with a `TrackedBlob` and a
+/// `Transform` under the board.
+///
+/// Add it alongside `TiltFivePlugin` - it streams the tangible camera of every
+/// pair of glasses that connects.
+#[derive(Default)]
+pub struct TiltFiveTrackingPlugin {
+    pub settings: BlobTrackingSettings,
+}
+
+impl Plugin for TiltFiveTrackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .register_type::<BlobTrackingSettings>()
+            .register_type::<TrackedBlob>()
+            .init_resource::<PendingFramePairs>()
+            .add_system(stream_tangible_camera)
+            .add_system(track_blobs);
+    }
+}
+
+/// How blobs are found and placed on the board - can be changed while running
+#[derive(Resource, Reflect, FromReflect, Debug, Clone)]
+#[reflect(Resource)]
+pub struct BlobTrackingSettings {
+    /// How much brighter a pixel has to be in the lit frame than the dark one
+    /// to be part of a blob
+    pub threshold: u8,
+    /// Smaller blobs are treated as noise
+    pub min_pixels: usize,
+    /// Larger blobs are something other than a sticker - a reflection off the
+    /// table, for example
+    pub max_pixels: usize,
+    /// The horizontal field of view of the tangible tracking camera, in degrees
+    pub horizontal_fov: f32,
+    /// How far above the board the stickers are, in meters
+    pub marker_height: f32,
+    /// A blob within this distance of one tracked in the last pair of frames
+    /// is taken to be the same sticker, in meters
+    pub match_distance: f32,
+    /// Keep a blob around for this many pairs of frames without seeing it,
+    /// so it doesn't flicker when a hand passes over it
+    pub max_missed_pairs: u32,
+}
+
+impl Default for BlobTrackingSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 64,
+            min_pixels: 4,
+            max_pixels: 2000,
+            horizontal_fov: 80.,
+            marker_height: 0.,
+            match_distance: 0.03,
+            max_missed_pairs: 3,
+        }
+    }
+}
+
+/// A bright spot found in a pair of camera frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blob {
+    /// The middle of the blob in pixels, weighted by how much brighter each
+    /// pixel is in the lit frame
+    pub center: Vec2,
+    pub pixels: usize,
+}
+
+/// A sticker being tracked on the board, seen by the camera of `glasses`
+#[derive(Component, Reflect, FromReflect, Debug, Clone)]
+pub struct TrackedBlob {
+    pub glasses: Glasses,
+    /// The size of the blob in the last frames it was seen in
+    pub pixels: usize,
+    /// How many pairs of frames in a row it's been missing from
+    pub missed_pairs: u32,
+}
+
+/// Find the blobs that are bright in `light` but not in `dark` - retroreflectors
+/// lit by the glasses, rather than lamps or windows that are bright in both.
+///
+/// Frames of different sizes have nothing in common, so they give no blobs.
+pub fn find_blobs(
+    light: &CameraImage,
+    dark: &CameraImage,
+    settings: &BlobTrackingSettings,
+) -> Vec<Blob> {
+    if light.width != dark.width || light.height != dark.height {
+        return vec![];
+    }
+    let (width, height) = (light.width as usize, light.height as usize);
+    let difference: Vec<u8> = light
+        .pixels
+        .iter()
+        .zip(dark.pixels.iter())
+        .map(|(light, dark)| light.saturating_sub(*dark))
+        .collect();
+    if difference.len() < width * height {
+        return vec![];
+    }
+
+    let mut visited = vec![false; width * height];
+    let mut blobs = vec![];
+    let mut stack = vec![];
+    for start in 0..width * height {
+        if visited[start] || difference[start] < settings.threshold {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+
+        let (mut weight, mut sum, mut pixels) = (0., Vec2::ZERO, 0);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            let w = difference[i] as f32;
+            weight += w;
+            sum += Vec2::new(x as f32, y as f32) * w;
+            pixels += 1;
+
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if !visited[n] && difference[n] >= settings.threshold {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        if (settings.min_pixels..=settings.max_pixels).contains(&pixels) {
+            blobs.push(Blob {
+                center: sum / weight,
+                pixels,
+            });
+        }
+    }
+    blobs
+}
+
+/// Where a blob seen at `center` in `image` is on the board, in gameboard space.
+///
+/// Follows the ray from the camera through the blob down to `marker_height`
+/// above the board - `None` if the ray never gets there.
+pub fn blob_board_position(
+    image: &CameraImage,
+    center: Vec2,
+    settings: &BlobTrackingSettings,
+) -> Option<Vec3> {
+    // The camera looks down its -z axis, with +y up - and the image's y goes down
+    let focal_length = image.width as f32 / 2. / (settings.horizontal_fov.to_radians() / 2.).tan();
+    let principal_point = Vec2::new(image.width as f32, image.height as f32) / 2.;
+    let offset = (center + Vec2::splat(0.5) - principal_point) / focal_length;
+    let direction = Vec3::new(offset.x, -offset.y, -1.);
+
+    // `rotation` takes gameboard space to the camera, so its inverse brings the ray back
+    let direction = image.rotation.inverse() * direction;
+    if direction.z.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = (settings.marker_height - image.position.z) / direction.z;
+    (distance > 0.).then(|| image.position + direction * distance)
+}
+
+/// The last lit and dark frames from each pair of glasses, waiting for the
+/// other half of their pair
+#[derive(Resource, Default)]
+struct PendingFramePairs(HashMap<Glasses, (Option<CameraImage>, Option<CameraImage>)>);
+
+fn stream_tangible_camera(
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut commands: EventWriter<TiltFiveCommands>,
+) {
+    for event in events.iter() {
+        if let TiltFiveClientEvent::GlassesConnected(glasses, _) = event {
+            commands.send(TiltFiveCommands::SetCameraStream(
+                glasses.clone(),
+                GlassesCamera::Tangible,
+                true,
+            ));
+        }
+    }
+}
+
+fn track_blobs(
+    mut commands: Commands,
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut pending: ResMut<PendingFramePairs>,
+    settings: Res<BlobTrackingSettings>,
+    boards: Query<Entity, With<BoardTransformer>>,
+    mut tracked: Query<(Entity, &mut TrackedBlob, &mut Transform)>,
+) {
+    let mut found: Vec<(Glasses, Vec<(Vec3, usize)>)> = vec![];
+    for event in events.iter() {
+        match event {
+            TiltFiveClientEvent::CameraImage(glasses, image)
+                if image.camera == GlassesCamera::Tangible =>
+            {
+                let frames = pending.0.entry(glasses.clone()).or_default();
+                match image.illumination {
+                    IlluminationMode::Light => frames.0 = Some(image.clone()),
+                    IlluminationMode::Dark => frames.1 = Some(image.clone()),
+                    IlluminationMode::Unknown => continue,
+                }
+                let (Some(light), Some(dark)) = frames else {
+                    continue;
+                };
+                let positions = find_blobs(light, dark, &settings)
+                    .into_iter()
+                    .filter_map(|blob| {
+                        blob_board_position(light, blob.center, &settings)
+                            .map(|position| (bevy_position_from_gameboard(position), blob.pixels))
+                    })
+                    .collect();
+                found.push((glasses.clone(), positions));
+                // Each frame is only used in one pair
+                *frames = Default::default();
+            }
+            TiltFiveClientEvent::GlassesDisconnected(glasses) => {
+                pending.0.remove(glasses);
+                for (entity, blob, _) in tracked.iter() {
+                    if blob.glasses == *glasses {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Ok(board) = boards.get_single() else {
+        return;
+    };
+    for (glasses, positions) in found {
+        let mut unmatched: Vec<Entity> = tracked
+            .iter()
+            .filter(|(_, blob, _)| blob.glasses == glasses)
+            .map(|(entity, _, _)| entity)
+            .collect();
+
+        for (position, pixels) in positions {
+            let nearest = unmatched
+                .iter()
+                .enumerate()
+                .filter_map(|(i, entity)| {
+                    let (_, _, transform) = tracked.get(*entity).ok()?;
+                    let distance = transform.translation.distance(position);
+                    (distance <= settings.match_distance).then_some((i, distance))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            match nearest {
+                Some((i, _)) => {
+                    let entity = unmatched.swap_remove(i);
+                    if let Ok((_, mut blob, mut transform)) = tracked.get_mut(entity) {
+                        transform.translation = position;
+                        blob.pixels = pixels;
+                        blob.missed_pairs = 0;
+                    }
+                }
+                None => {
+                    commands.entity(board).with_children(|parent| {
+                        parent.spawn((
+                            TrackedBlob {
+                                glasses: glasses.clone(),
+                                pixels,
+                                missed_pairs: 0,
+                            },
+                            SpatialBundle::from_transform(Transform::from_translation(position)),
+                        ));
+                    });
+                }
+            }
+        }
+
+        for entity in unmatched {
+            if let Ok((_, mut blob, _)) = tracked.get_mut(entity) {
+                blob.missed_pairs += 1;
+                if blob.missed_pairs > settings.max_missed_pairs {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backend::{connect, run_frames, test_app},
+        FakeBackend, RecordedSample, Recording, RecordingBackend, TiltFiveBackend,
+    };
+
+    use super::*;
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 12;
+
+    /// A frame from a camera half a meter above the middle of the board,
+    /// looking straight down, with a lamp in the top left corner that's on in
+    /// both frames - and stickers that only show up in the lit one
+    fn frame(illumination: IlluminationMode, stickers: &[(u32, u32)]) -> CameraImage {
+        let mut pixels = vec![10; (WIDTH * HEIGHT) as usize];
+        for y in 0..3 {
+            for x in 0..3 {
+                pixels[(y * WIDTH + x) as usize] = 250;
+            }
+        }
+        if illumination == IlluminationMode::Light {
+            for (x, y) in stickers {
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    pixels[((y + dy) * WIDTH + x + dx) as usize] = 200;
+                }
+            }
+        }
+        CameraImage {
+            camera: GlassesCamera::Tangible,
+            illumination,
+            width: WIDTH,
+            height: HEIGHT,
+            pixels,
+            position: Vec3::new(0., 0., 0.5),
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    #[test]
+    fn finds_blobs_only_lit_by_the_glasses() {
+        let settings = BlobTrackingSettings::default();
+        let light = frame(IlluminationMode::Light, &[(5, 5), (11, 2)]);
+        let dark = frame(IlluminationMode::Dark, &[]);
+
+        let blobs = find_blobs(&light, &dark, &settings);
+        assert_eq!(
+            blobs,
+            vec![
+                Blob {
+                    center: Vec2::new(11.5, 2.5),
+                    pixels: 4,
+                },
+                Blob {
+                    center: Vec2::new(5.5, 5.5),
+                    pixels: 4,
+                },
+            ]
+        );
+
+        let too_big = BlobTrackingSettings {
+            max_pixels: 3,
+            ..default()
+        };
+        assert!(find_blobs(&light, &dark, &too_big).is_empty());
+    }
+
+    #[test]
+    fn follows_blobs_down_to_the_board() {
+        let settings = BlobTrackingSettings {
+            horizontal_fov: 90.,
+            ..default()
+        };
+        let image = frame(IlluminationMode::Light, &[]);
+
+        // The middle of the image is straight below the camera
+        let position = blob_board_position(&image, Vec2::new(7.5, 5.5), &settings).unwrap();
+        assert!(position.distance(Vec3::ZERO) < 1e-5);
+
+        // With a 90 degree field of view the right edge is as far out as the
+        // camera is high - and up in the image is forward on the board
+        let position = blob_board_position(&image, Vec2::new(15.5, 5.5), &settings).unwrap();
+        assert!(position.distance(Vec3::new(0.5, 0., 0.)) < 1e-5);
+        let position = blob_board_position(&image, Vec2::new(7.5, -0.5), &settings).unwrap();
+        assert!(position.distance(Vec3::new(0., 0.375, 0.)) < 1e-5);
+
+        // A camera looking up never sees the board
+        let upside_down = CameraImage {
+            rotation: Quat::from_rotation_x(std::f32::consts::PI),
+            ..image
+        };
+        assert!(blob_board_position(&upside_down, Vec2::new(7.5, 5.5), &settings).is_none());
+    }
+
+    #[test]
+    fn finds_blobs_in_recorded_frames() {
+        let glasses: Glasses = "recorded-tracking-glasses".into();
+        let fake = FakeBackend::new();
+        fake.add_glasses(glasses.clone(), None);
+        let mut recorder = RecordingBackend::new(fake.clone(), vec![]).unwrap();
+        while recorder.connect_glasses(&glasses).unwrap() != crate::ConnectionProgress::Ready {}
+        recorder
+            .configure_camera_stream(&glasses, GlassesCamera::Tangible, true)
+            .unwrap();
+        fake.push_camera_image(&glasses, frame(IlluminationMode::Light, &[(5, 5)]));
+        fake.push_camera_image(&glasses, frame(IlluminationMode::Dark, &[]));
+        recorder.get_camera_images(&glasses).unwrap();
+        let (_, bytes) = recorder.finish().unwrap();
+
+        let recording = Recording::read_from(bytes.unwrap().as_slice()).unwrap();
+        let images: Vec<&CameraImage> = recording
+            .frames
+            .iter()
+            .filter_map(|frame| match &frame.sample {
+                RecordedSample::CameraImage(image) => Some(image),
+                _ => None,
+            })
+            .collect();
+        let [light, dark] = images[..] else {
+            panic!("both frames should be recorded");
+        };
+
+        let blobs = find_blobs(light, dark, &BlobTrackingSettings::default());
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(blobs[0].center, Vec2::new(5.5, 5.5));
+    }
+
+    #[test]
+    fn spawns_tracked_blobs_under_the_board() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "tracking-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        let mut app = test_app(backend.clone());
+        app.add_plugin(TiltFiveTrackingPlugin {
+            settings: BlobTrackingSettings {
+                horizontal_fov: 90.,
+                ..default()
+            },
+        });
+        connect(&mut app, &glasses);
+        assert_eq!(
+            backend.with_glasses(&glasses, |g| g.camera_streams.clone()),
+            Some(vec![GlassesCamera::Tangible])
+        );
+
+        backend.push_camera_image(&glasses, frame(IlluminationMode::Light, &[(14, 5)]));
+        backend.push_camera_image(&glasses, frame(IlluminationMode::Dark, &[]));
+        run_frames(&mut app);
+
+        let board = app
+            .world
+            .query_filtered::<Entity, With<BoardTransformer>>()
+            .single(&app.world);
+        let mut blobs = app.world.query::<(&TrackedBlob, &Transform, &Parent)>();
+        let (blob, transform, parent) = blobs.single(&app.world);
+        assert_eq!(blob.glasses, glasses);
+        assert_eq!(parent.get(), board);
+        // A sticker off to the right of the camera, in the board's bevy space
+        assert!(transform.translation.x > 0.4);
+        assert!(transform.translation.y.abs() < 1e-5);
+
+        // Once it's been gone for long enough it stops being tracked
+        for _ in 0..=BlobTrackingSettings::default().max_missed_pairs {
+            backend.push_camera_image(&glasses, frame(IlluminationMode::Light, &[]));
+            backend.push_camera_image(&glasses, frame(IlluminationMode::Dark, &[]));
+            run_frames(&mut app);
+        }
+        assert_eq!(blobs.iter(&app.world).count(), 0);
+    }
+}
@@ -0,0 +1,368 @@
+use bevy::prelude::*;
+
+use crate::{Board, Gameboard, T5GameboardType};
+
+/// Keeps content inside the part of the board the glasses can show it on.
+///
+/// The optics hard-cut anything that drifts past the edge of the board, which
+/// looks broken - entities with a `ViewableAreaContent` are hidden or faded out
+/// before they get there instead, and a `LeftViewableArea` or
+/// `EnteredViewableArea` event is sent whenever one crosses the edge.
+///
+/// Nothing is hidden until the glasses have seen a board.
+pub struct TiltFiveViewableAreaPlugin;
+
+impl Plugin for TiltFiveViewableAreaPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OutOfBounds>()
+            .register_type::<ViewableAreaContent>()
+            .add_event::<LeftViewableArea>()
+            .add_event::<EnteredViewableArea>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                check_viewable_area
+                    .after(bevy::transform::TransformSystem::TransformPropagate)
+                    .before(bevy::render::view::VisibilitySystems::VisibilityPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                fade_materials.after(check_viewable_area),
+            );
+    }
+}
+
+/// What happens to content once it's outside the viewable area
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq)]
+pub enum OutOfBounds {
+    /// Leave it be - only send the events
+    Report,
+    /// Hide it as soon as it's outside
+    Clip,
+    /// Fade its `StandardMaterial` out over this many meters approaching the
+    /// edge, so it's gone by the time it gets there
+    Fade(f32),
+}
+
+/// Marks content that should stay inside the board's viewable area
+#[derive(Component, Reflect, FromReflect, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct ViewableAreaContent {
+    pub out_of_bounds: OutOfBounds,
+    /// Whether it was inside the viewable area when last checked
+    pub inside: bool,
+    /// How visible it should be, from 0 at the edge to 1 well inside - for
+    /// content drawn with something other than a `StandardMaterial`
+    pub opacity: f32,
+}
+
+impl ViewableAreaContent {
+    pub fn new(out_of_bounds: OutOfBounds) -> Self {
+        Self {
+            out_of_bounds,
+            inside: true,
+            opacity: 1.,
+        }
+    }
+}
+
+impl Default for ViewableAreaContent {
+    fn default() -> Self {
+        Self::new(OutOfBounds::Fade(0.05))
+    }
+}
+
+/// Sent when content moves out past the edge of the viewable area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeftViewableArea(pub Entity);
+
+/// Sent when content comes back inside the viewable area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnteredViewableArea(pub Entity);
+
+/// The alpha a faded material had before it was first faded, so it can be
+/// restored - each faded entity gets its own copy of its material
+#[derive(Component, Clone, Copy)]
+struct FadedMaterial {
+    alpha: f32,
+    alpha_mode: AlphaMode,
+}
+
+/// Marks content this plugin hid, so it's only shown again if it was hidden
+/// here - content the game hides itself is left hidden
+#[derive(Component)]
+struct HiddenByViewableArea;
+
+type ContentQuery<'a> = (
+    Entity,
+    &'a GlobalTransform,
+    &'a mut ViewableAreaContent,
+    Option<&'a mut Visibility>,
+    Option<&'a HiddenByViewableArea>,
+);
+
+type FadedContentQuery<'a> = (
+    Entity,
+    &'a ViewableAreaContent,
+    &'a mut Handle<StandardMaterial>,
+    Option<&'a FadedMaterial>,
+);
+
+fn check_viewable_area(
+    mut commands: Commands,
+    boards: Query<(&GlobalTransform, &Gameboard), With<Board>>,
+    mut content: Query<ContentQuery>,
+    mut left: EventWriter<LeftViewableArea>,
+    mut entered: EventWriter<EnteredViewableArea>,
+) {
+    let Ok((board_transform, gameboard)) = boards.get_single() else {
+        return;
+    };
+    if gameboard.board_type == T5GameboardType::None {
+        return;
+    }
+    let to_board = board_transform.affine().inverse();
+
+    for (entity, transform, mut content, visibility, hidden) in content.iter_mut() {
+        let position = to_board.transform_point3(transform.translation());
+        let distance = gameboard.distance_inside(position);
+        let inside = distance >= 0.;
+
+        if inside != content.inside {
+            content.inside = inside;
+            if inside {
+                entered.send(EnteredViewableArea(entity));
+            } else {
+                left.send(LeftViewableArea(entity));
+            }
+        }
+
+        let opacity = match content.out_of_bounds {
+            OutOfBounds::Fade(fade) if fade > 0. => (distance / fade).clamp(0., 1.),
+            _ if inside => 1.,
+            _ => 0.,
+        };
+        if content.opacity != opacity {
+            content.opacity = opacity;
+        }
+
+        if let (OutOfBounds::Clip | OutOfBounds::Fade(_), Some(mut visibility)) =
+            (content.out_of_bounds, visibility)
+        {
+            let visible = opacity > 0.;
+            if !visible && visibility.is_visible {
+                visibility.is_visible = false;
+                commands.entity(entity).insert(HiddenByViewableArea);
+            } else if visible && hidden.is_some() {
+                visibility.is_visible = true;
+                commands.entity(entity).remove::<HiddenByViewableArea>();
+            }
+        }
+    }
+}
+
+fn fade_materials(
+    mut commands: Commands,
+    mut content: Query<FadedContentQuery, Changed<ViewableAreaContent>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, content, mut handle, faded) in content.iter_mut() {
+        if !matches!(content.out_of_bounds, OutOfBounds::Fade(_)) {
+            continue;
+        }
+        let faded = match faded {
+            Some(faded) => *faded,
+            None if content.opacity >= 1. => continue,
+            None => {
+                let Some(material) = materials.get(&handle).cloned() else {
+                    continue;
+                };
+                let faded = FadedMaterial {
+                    alpha: material.base_color.a(),
+                    alpha_mode: material.alpha_mode,
+                };
+                *handle = materials.add(material);
+                commands.entity(entity).insert(faded);
+                faded
+            }
+        };
+        if let Some(material) = materials.get_mut(&handle) {
+            fade(material, &faded, content.opacity);
+        }
+    }
+}
+
+fn fade(material: &mut StandardMaterial, faded: &FadedMaterial, opacity: f32) {
+    material.base_color.set_a(faded.alpha * opacity);
+    material.alpha_mode = if opacity < 1. {
+        AlphaMode::Blend
+    } else {
+        faded.alpha_mode
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{backend::test_app, FakeBackend};
+
+    use super::*;
+
+    fn viewable_area_app() -> (App, Entity) {
+        let mut app = test_app(FakeBackend::new());
+        app.add_plugin(bevy::transform::TransformPlugin)
+            .add_plugin(TiltFiveViewableAreaPlugin);
+        let mut boards = app.world.query_filtered::<Entity, With<Board>>();
+        let board = boards.single(&app.world);
+        app.world.entity_mut(board).insert(Gameboard {
+            board_type: T5GameboardType::LE,
+            min: Vec3::new(-0.35, 0., -0.35),
+            max: Vec3::new(0.35, 0., 0.35),
+        });
+        (app, board)
+    }
+
+    fn events(app: &App) -> (Vec<Entity>, Vec<Entity>) {
+        let left = app.world.resource::<Events<LeftViewableArea>>();
+        let entered = app.world.resource::<Events<EnteredViewableArea>>();
+        (
+            left.iter_current_update_events().map(|e| e.0).collect(),
+            entered.iter_current_update_events().map(|e| e.0).collect(),
+        )
+    }
+
+    #[test]
+    fn measures_how_far_inside_the_board_content_is() {
+        let flat = Gameboard {
+            board_type: T5GameboardType::LE,
+            min: Vec3::new(-0.35, 0., -0.35),
+            max: Vec3::new(0.35, 0., 0.35),
+        };
+        assert!((flat.distance_inside(Vec3::new(0.3, 0., 0.)) - 0.05).abs() < 1e-5);
+        // Flat boards don't limit how high or low content goes
+        assert!((flat.distance_inside(Vec3::new(0., 2., 0.)) - 0.35).abs() < 1e-5);
+        assert!((flat.distance_inside(Vec3::new(0.1, -0.1, 0.)) - 0.25).abs() < 1e-5);
+        assert!(flat.distance_inside(Vec3::new(0., 0., 0.4)) < 0.);
+
+        let raised = Gameboard {
+            board_type: T5GameboardType::XeRaised,
+            min: Vec3::new(-0.7, 0., -0.35),
+            max: Vec3::new(0.7, 0.5, 0.35),
+        };
+        assert!(raised.distance_inside(Vec3::new(0., 0.6, 0.)) < 0.);
+    }
+
+    #[test]
+    fn clips_content_and_reports_crossing_the_edge() {
+        let (mut app, board) = viewable_area_app();
+        let content = app
+            .world
+            .spawn((
+                SpatialBundle::default(),
+                ViewableAreaContent::new(OutOfBounds::Clip),
+            ))
+            .id();
+        app.world.entity_mut(board).push_children(&[content]);
+        app.update();
+        assert_eq!(events(&app), (vec![], vec![]));
+        assert!(app.world.get::<Visibility>(content).unwrap().is_visible);
+
+        app.world
+            .get_mut::<Transform>(content)
+            .unwrap()
+            .translation
+            .x = 0.5;
+        app.update();
+        assert_eq!(events(&app), (vec![content], vec![]));
+        assert!(!app.world.get::<Visibility>(content).unwrap().is_visible);
+
+        app.world
+            .get_mut::<Transform>(content)
+            .unwrap()
+            .translation
+            .x = 0.;
+        app.update();
+        assert_eq!(events(&app), (vec![], vec![content]));
+        assert!(app.world.get::<Visibility>(content).unwrap().is_visible);
+
+        // Content the game hid itself stays hidden, wherever it is
+        app.world.get_mut::<Visibility>(content).unwrap().is_visible = false;
+        app.update();
+        assert!(!app.world.get::<Visibility>(content).unwrap().is_visible);
+        app.world
+            .get_mut::<Transform>(content)
+            .unwrap()
+            .translation
+            .x = 0.5;
+        app.update();
+        app.world
+            .get_mut::<Transform>(content)
+            .unwrap()
+            .translation
+            .x = 0.;
+        app.update();
+        assert!(!app.world.get::<Visibility>(content).unwrap().is_visible);
+    }
+
+    #[test]
+    fn fades_content_near_the_edge() {
+        let (mut app, board) = viewable_area_app();
+        let shared = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::RED.into());
+        let content = app
+            .world
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_xyz(0., 0., 0.325)),
+                shared.clone(),
+                ViewableAreaContent::new(OutOfBounds::Fade(0.05)),
+            ))
+            .id();
+        app.world.entity_mut(board).push_children(&[content]);
+        app.update();
+
+        let content_state = *app.world.get::<ViewableAreaContent>(content).unwrap();
+        assert!(content_state.inside);
+        assert!((content_state.opacity - 0.5).abs() < 1e-4);
+
+        // Fading gives the content its own material, leaving the shared one be
+        let handle = app
+            .world
+            .get::<Handle<StandardMaterial>>(content)
+            .unwrap()
+            .clone();
+        assert_ne!(handle, shared);
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        assert_eq!(materials.get(&shared).unwrap().base_color.a(), 1.);
+        let material = materials.get(&handle).unwrap();
+        assert!((material.base_color.a() - 0.5).abs() < 1e-4);
+        assert_eq!(material.alpha_mode, AlphaMode::Blend);
+
+        // Back in the middle it's drawn just as it was
+        app.world
+            .get_mut::<Transform>(content)
+            .unwrap()
+            .translation
+            .z = 0.;
+        app.update();
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        let material = materials.get(&handle).unwrap();
+        assert_eq!(material.base_color.a(), 1.);
+        assert_eq!(material.alpha_mode, AlphaMode::Opaque);
+    }
+
+    #[test]
+    fn leaves_content_alone_until_a_board_is_seen() {
+        let (mut app, board) = viewable_area_app();
+        app.world.entity_mut(board).insert(Gameboard::default());
+        let content = app
+            .world
+            .spawn((
+                SpatialBundle::from_transform(Transform::from_xyz(2., 0., 0.)),
+                ViewableAreaContent::new(OutOfBounds::Clip),
+            ))
+            .id();
+        app.update();
+        assert_eq!(events(&app), (vec![], vec![]));
+        assert!(app.world.get::<Visibility>(content).unwrap().is_visible);
+    }
+}
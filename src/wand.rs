@@ -0,0 +1,614 @@
+use std::time::Duration;
+
+use bevy::{
+    input::gamepad::{GamepadEventRaw, GamepadEventType, GamepadInfo},
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::{
+    bridge::ffi::{T5_Hand, T5_Hand_kT5_Hand_Left, T5_Hand_kT5_Hand_Right, T5_WandReport},
+    conversions::{bevy_position_from_gameboard, bevy_transform_from_gameboard_pose},
+    Glasses, TiltFiveClientEvent, TiltFiveWand,
+};
+
+/// Wands are registered as gamepads from this id up, clear of the ids gilrs
+/// gives real gamepads
+pub const WAND_GAMEPAD_ID_START: usize = 1 << 16;
+
+/// The state of a wand, as of one report from the wand stream.
+///
+/// Each section is only there when the report says it's valid.
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WandReport {
+    /// When the SDK took the report, on its own clock
+    pub timestamp: Duration,
+    pub analog: Option<WandAnalog>,
+    /// How charged the battery is, from 0 to 1
+    pub battery: Option<f32>,
+    pub buttons: Option<WandButtons>,
+    pub pose: Option<WandPose>,
+    /// The hand the wand is held in
+    pub hand: Hand,
+}
+
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WandAnalog {
+    /// From 0 released to 1 fully pulled
+    pub trigger: f32,
+    /// From -1 to 1 on each axis, +x right and +y forward
+    pub stick: Vec2,
+}
+
+/// Where the wand is, in the space of the entities under the board - the same
+/// space the wand's `Transform` is in
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WandPose {
+    pub rotation: Quat,
+    /// The point the wand aims from, past its tip
+    pub aim: Vec3,
+    /// Where the fingertips rest on the wand
+    pub fingertips: Vec3,
+    /// The middle of the hand holding the wand
+    pub grip: Vec3,
+}
+
+impl WandPose {
+    /// The wand's pose at its grip, looking where it points
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.grip).with_rotation(self.rotation)
+    }
+}
+
+/// The buttons held down on a wand
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WandButtons(u8);
+
+bitflags::bitflags! {
+    impl WandButtons: u8 {
+        const T5 = 1 << 0;
+        const ONE = 1 << 1;
+        const TWO = 1 << 2;
+        const THREE = 1 << 3;
+        const A = 1 << 4;
+        const B = 1 << 5;
+        const X = 1 << 6;
+        const Y = 1 << 7;
+    }
+}
+
+#[derive(Reflect, FromReflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    #[default]
+    Unknown,
+    Left,
+    Right,
+}
+
+impl From<T5_Hand> for Hand {
+    #[allow(non_upper_case_globals)]
+    fn from(value: T5_Hand) -> Self {
+        match value {
+            T5_Hand_kT5_Hand_Left => Hand::Left,
+            T5_Hand_kT5_Hand_Right => Hand::Right,
+            _ => Hand::Unknown,
+        }
+    }
+}
+
+/// A report with nothing filled in
+impl Default for T5_WandReport {
+    fn default() -> Self {
+        // Every field of a report is valid as zero
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+impl From<&T5_WandReport> for WandReport {
+    fn from(report: &T5_WandReport) -> Self {
+        let buttons = &report.buttons;
+        Self {
+            timestamp: Duration::from_nanos(report.timestampNanos),
+            analog: report.analogValid.then(|| WandAnalog {
+                trigger: report.trigger,
+                stick: Vec2::new(report.stick.x, report.stick.y),
+            }),
+            battery: report
+                .batteryValid
+                .then(|| report.battery as f32 / u8::MAX as f32),
+            buttons: report.buttonsValid.then(|| {
+                [
+                    (buttons.t5, WandButtons::T5),
+                    (buttons.one, WandButtons::ONE),
+                    (buttons.two, WandButtons::TWO),
+                    (buttons.three, WandButtons::THREE),
+                    (buttons.a, WandButtons::A),
+                    (buttons.b, WandButtons::B),
+                    (buttons.x, WandButtons::X),
+                    (buttons.y, WandButtons::Y),
+                ]
+                .into_iter()
+                .filter(|(pressed, _)| *pressed)
+                .fold(WandButtons::empty(), |held, (_, button)| held | button)
+            }),
+            pose: report.poseValid.then(|| {
+                let grip = bevy_transform_from_gameboard_pose(
+                    report.posGrip_GBD.into(),
+                    report.rotToWND_GBD.into(),
+                );
+                WandPose {
+                    rotation: grip.rotation,
+                    aim: bevy_position_from_gameboard(report.posAim_GBD.into()),
+                    fingertips: bevy_position_from_gameboard(report.posFingertips_GBD.into()),
+                    grip: grip.translation,
+                }
+            }),
+            hand: report.hand.into(),
+        }
+    }
+}
+
+/// A button on a particular wand, for reading wands through
+/// `Input<WandButton>` the way gamepads are read through `Input<GamepadButton>`
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WandButton {
+    /// The wand's `TiltFiveWand` entity
+    pub wand: Entity,
+    pub button_type: WandButtonType,
+}
+
+impl WandButton {
+    pub fn new(wand: Entity, button_type: WandButtonType) -> Self {
+        Self { wand, button_type }
+    }
+}
+
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WandButtonType {
+    T5,
+    One,
+    Two,
+    Three,
+    A,
+    B,
+    X,
+    Y,
+}
+
+impl WandButtonType {
+    pub const ALL: [WandButtonType; 8] = [
+        WandButtonType::T5,
+        WandButtonType::One,
+        WandButtonType::Two,
+        WandButtonType::Three,
+        WandButtonType::A,
+        WandButtonType::B,
+        WandButtonType::X,
+        WandButtonType::Y,
+    ];
+
+    /// The gamepad button this button is reported as when the wand is read as
+    /// a `Gamepad` - the face buttons by position, T5 as `Mode` and 1/2/3,
+    /// which gamepads don't have, as `Other`
+    pub fn gamepad_button_type(&self) -> GamepadButtonType {
+        match self {
+            WandButtonType::T5 => GamepadButtonType::Mode,
+            WandButtonType::One => GamepadButtonType::Other(1),
+            WandButtonType::Two => GamepadButtonType::Other(2),
+            WandButtonType::Three => GamepadButtonType::Other(3),
+            WandButtonType::A => GamepadButtonType::South,
+            WandButtonType::B => GamepadButtonType::East,
+            WandButtonType::X => GamepadButtonType::West,
+            WandButtonType::Y => GamepadButtonType::North,
+        }
+    }
+}
+
+impl From<WandButtonType> for WandButtons {
+    fn from(button_type: WandButtonType) -> Self {
+        match button_type {
+            WandButtonType::T5 => WandButtons::T5,
+            WandButtonType::One => WandButtons::ONE,
+            WandButtonType::Two => WandButtons::TWO,
+            WandButtonType::Three => WandButtons::THREE,
+            WandButtonType::A => WandButtons::A,
+            WandButtonType::B => WandButtons::B,
+            WandButtonType::X => WandButtons::X,
+            WandButtonType::Y => WandButtons::Y,
+        }
+    }
+}
+
+/// An analog input on a particular wand, for reading through `Axis<WandAxis>`
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WandAxis {
+    /// The wand's `TiltFiveWand` entity
+    pub wand: Entity,
+    pub axis_type: WandAxisType,
+}
+
+impl WandAxis {
+    pub fn new(wand: Entity, axis_type: WandAxisType) -> Self {
+        Self { wand, axis_type }
+    }
+}
+
+#[derive(Reflect, FromReflect, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WandAxisType {
+    /// From 0 released to 1 fully pulled
+    Trigger,
+    /// From -1 left to 1 right
+    StickX,
+    /// From -1 back to 1 forward
+    StickY,
+}
+
+/// Feed the wand reports from last frame into `Input<WandButton>` and
+/// `Axis<WandAxis>`. Runs in `PreUpdate`, like Bevy's own input, so the
+/// `just_pressed` and `just_released` state lasts the whole frame.
+pub(crate) fn update_wand_input(
+    mut events: EventReader<TiltFiveClientEvent>,
+    wands: Query<(Entity, &TiltFiveWand)>,
+    mut buttons: ResMut<Input<WandButton>>,
+    mut axes: ResMut<Axis<WandAxis>>,
+    mut known: Local<HashSet<Entity>>,
+) {
+    buttons.clear();
+
+    for event in events.iter() {
+        let TiltFiveClientEvent::WantReportUpdated {
+            glasses,
+            wand_id,
+            report,
+        } = event
+        else {
+            continue;
+        };
+        let Some((wand, _)) = wands
+            .iter()
+            .find(|(_, wand)| &wand.glasses == glasses && &wand.wand_id == wand_id)
+        else {
+            continue;
+        };
+        known.insert(wand);
+
+        if let Some(held) = report.buttons {
+            for button_type in WandButtonType::ALL {
+                let button = WandButton::new(wand, button_type);
+                if held.contains(button_type.into()) {
+                    buttons.press(button);
+                } else {
+                    buttons.release(button);
+                }
+            }
+        }
+        if let Some(analog) = report.analog {
+            axes.set(WandAxis::new(wand, WandAxisType::Trigger), analog.trigger);
+            axes.set(WandAxis::new(wand, WandAxisType::StickX), analog.stick.x);
+            axes.set(WandAxis::new(wand, WandAxisType::StickY), analog.stick.y);
+        }
+    }
+
+    // Wands that are gone let go of everything
+    known.retain(|wand| {
+        if wands.get(*wand).is_ok() {
+            return true;
+        }
+        for button_type in WandButtonType::ALL {
+            buttons.release(WandButton::new(*wand, button_type));
+        }
+        for axis_type in [
+            WandAxisType::Trigger,
+            WandAxisType::StickX,
+            WandAxisType::StickY,
+        ] {
+            axes.remove(WandAxis::new(*wand, axis_type));
+        }
+        false
+    });
+}
+
+/// A wand registered as a Bevy `Gamepad`
+#[derive(Reflect, FromReflect, Debug, Clone, PartialEq, Eq)]
+pub struct WandGamepad {
+    pub glasses: Glasses,
+    pub wand_id: String,
+    pub gamepad: Gamepad,
+}
+
+/// The `Gamepad` each connected wand is registered as, so gameplay that reads
+/// gamepads picks up wands without knowing about them. The stick is the left
+/// stick, the trigger `RightTrigger2` and the buttons are mapped by
+/// `WandButtonType::gamepad_button_type`.
+#[derive(Resource, Reflect, Debug, Clone, Default)]
+#[reflect(Resource)]
+pub struct WandGamepads {
+    pub wands: Vec<WandGamepad>,
+}
+
+impl WandGamepads {
+    pub fn gamepad(&self, glasses: &Glasses, wand_id: &str) -> Option<Gamepad> {
+        self.wands
+            .iter()
+            .find(|wand| &wand.glasses == glasses && wand.wand_id == wand_id)
+            .map(|wand| wand.gamepad)
+    }
+
+    /// The lowest wand gamepad id not in use, so a wand that reconnects
+    /// usually gets its old id back
+    fn next_gamepad(&self) -> Gamepad {
+        let id = (WAND_GAMEPAD_ID_START..)
+            .find(|id| self.wands.iter().all(|wand| wand.gamepad.id != *id))
+            .unwrap_or(WAND_GAMEPAD_ID_START);
+        Gamepad::new(id)
+    }
+}
+
+/// Turn the wand stream from last frame into raw gamepad events, ahead of
+/// Bevy's gamepad systems reading them
+pub(crate) fn update_wand_gamepads(
+    mut events: EventReader<TiltFiveClientEvent>,
+    mut gamepads: ResMut<WandGamepads>,
+    mut raw_events: EventWriter<GamepadEventRaw>,
+) {
+    for event in events.iter() {
+        match event {
+            TiltFiveClientEvent::WandConnected { glasses, wand_id } => {
+                if gamepads.gamepad(glasses, wand_id).is_some() {
+                    continue;
+                }
+                let gamepad = gamepads.next_gamepad();
+                gamepads.wands.push(WandGamepad {
+                    glasses: glasses.clone(),
+                    wand_id: wand_id.clone(),
+                    gamepad,
+                });
+                raw_events.send(GamepadEventRaw::new(
+                    gamepad,
+                    GamepadEventType::Connected(GamepadInfo {
+                        name: format!("Tilt Five Wand {wand_id} ({glasses})"),
+                    }),
+                ));
+            }
+            TiltFiveClientEvent::WantReportUpdated {
+                glasses,
+                wand_id,
+                report,
+            } => {
+                let Some(gamepad) = gamepads.gamepad(glasses, wand_id) else {
+                    continue;
+                };
+                let mut send =
+                    |event_type| raw_events.send(GamepadEventRaw::new(gamepad, event_type));
+                if let Some(held) = report.buttons {
+                    for button_type in WandButtonType::ALL {
+                        let value = if held.contains(button_type.into()) {
+                            1.
+                        } else {
+                            0.
+                        };
+                        send(GamepadEventType::ButtonChanged(
+                            button_type.gamepad_button_type(),
+                            value,
+                        ));
+                    }
+                }
+                if let Some(analog) = report.analog {
+                    send(GamepadEventType::ButtonChanged(
+                        GamepadButtonType::RightTrigger2,
+                        analog.trigger,
+                    ));
+                    send(GamepadEventType::AxisChanged(
+                        GamepadAxisType::LeftStickX,
+                        analog.stick.x,
+                    ));
+                    send(GamepadEventType::AxisChanged(
+                        GamepadAxisType::LeftStickY,
+                        analog.stick.y,
+                    ));
+                }
+            }
+            TiltFiveClientEvent::WandDisconnected { glasses, wand_id } => {
+                gamepads.wands.retain(|wand| {
+                    if &wand.glasses == glasses && &wand.wand_id == wand_id {
+                        raw_events.send(GamepadEventRaw::new(
+                            wand.gamepad,
+                            GamepadEventType::Disconnected,
+                        ));
+                        return false;
+                    }
+                    true
+                });
+            }
+            TiltFiveClientEvent::GlassesDisconnected(glasses) => {
+                gamepads.wands.retain(|wand| {
+                    if &wand.glasses == glasses {
+                        raw_events.send(GamepadEventRaw::new(
+                            wand.gamepad,
+                            GamepadEventType::Disconnected,
+                        ));
+                        return false;
+                    }
+                    true
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bevy::input::InputPlugin;
+
+    use super::*;
+    use crate::backend::{connected_app, poll_worker, run_frames};
+
+    #[test]
+    fn converts_reports_from_the_sdk() {
+        let mut raw = T5_WandReport {
+            timestampNanos: 2_000_000,
+            analogValid: true,
+            trigger: 0.5,
+            stick: Vec2::new(-1., 0.).into(),
+            buttonsValid: true,
+            poseValid: true,
+            posGrip_GBD: Vec3::new(0.1, 0.2, 0.3).into(),
+            posAim_GBD: Vec3::new(0.1, 0.4, 0.3).into(),
+            rotToWND_GBD: Quat::from_rotation_x(PI / 2.).into(),
+            hand: T5_Hand_kT5_Hand_Left,
+            ..Default::default()
+        };
+        raw.buttons.t5 = true;
+        raw.buttons.y = true;
+
+        let report = WandReport::from(&raw);
+        assert_eq!(report.timestamp, Duration::from_millis(2));
+        assert_eq!(
+            report.analog,
+            Some(WandAnalog {
+                trigger: 0.5,
+                stick: Vec2::new(-1., 0.),
+            })
+        );
+        // Sections the SDK didn't fill in are left out
+        assert_eq!(report.battery, None);
+        assert_eq!(report.buttons, Some(WandButtons::T5 | WandButtons::Y));
+        assert_eq!(report.hand, Hand::Left);
+
+        let pose = report.pose.unwrap();
+        assert!((pose.grip - Vec3::new(0.1, 0.3, -0.2)).length() < 1e-5);
+        assert!((pose.aim - Vec3::new(0.1, 0.3, -0.4)).length() < 1e-5);
+        // Pointing away from the player, towards where it aims from
+        assert!((pose.transform().forward() - Vec3::NEG_Z).length() < 1e-5);
+
+        raw.batteryValid = true;
+        raw.battery = u8::MAX;
+        raw.poseValid = false;
+        let report = WandReport::from(&raw);
+        assert_eq!(report.battery, Some(1.));
+        assert_eq!(report.pose, None);
+    }
+
+    #[test]
+    fn feeds_wand_reports_into_input() {
+        let (mut app, backend, glasses, _) = connected_app("wand-input-glasses");
+        backend.connect_wand(&glasses, 4);
+        run_frames(&mut app);
+        let wand = app
+            .world
+            .query_filtered::<Entity, With<TiltFiveWand>>()
+            .single(&app.world);
+        let a = WandButton::new(wand, WandButtonType::A);
+        let trigger = WandAxis::new(wand, WandAxisType::Trigger);
+
+        // Step a frame at a time, so the frame the press lands in can be seen
+        let step_until = |app: &mut App, done: &dyn Fn(&Input<WandButton>) -> bool| {
+            for _ in 0..5 {
+                app.update();
+                if done(app.world.resource::<Input<WandButton>>()) {
+                    return;
+                }
+                poll_worker(app);
+            }
+            panic!("the wand input never changed");
+        };
+
+        let mut report = T5_WandReport {
+            buttonsValid: true,
+            analogValid: true,
+            trigger: 0.75,
+            stick: Vec2::new(0., -0.5).into(),
+            ..Default::default()
+        };
+        report.buttons.a = true;
+        backend.push_wand_report(&glasses, 4, report);
+        step_until(&mut app, &|input| input.pressed(a));
+        let input = app.world.resource::<Input<WandButton>>();
+        assert!(input.just_pressed(a));
+        assert!(!input.pressed(WandButton::new(wand, WandButtonType::B)));
+        let axes = app.world.resource::<Axis<WandAxis>>();
+        assert_eq!(axes.get(trigger), Some(0.75));
+        assert_eq!(
+            axes.get(WandAxis::new(wand, WandAxisType::StickY)),
+            Some(-0.5)
+        );
+
+        // Held down, but no longer just pressed
+        app.update();
+        let input = app.world.resource::<Input<WandButton>>();
+        assert!(input.pressed(a));
+        assert!(!input.just_pressed(a));
+
+        report.buttons.a = false;
+        backend.push_wand_report(&glasses, 4, report);
+        step_until(&mut app, &|input| !input.pressed(a));
+        assert!(app.world.resource::<Input<WandButton>>().just_released(a));
+
+        // A disconnected wand lets go of everything
+        report.buttons.a = true;
+        backend.push_wand_report(&glasses, 4, report);
+        step_until(&mut app, &|input| input.pressed(a));
+        backend.disconnect_wand(&glasses, 4);
+        step_until(&mut app, &|input| !input.pressed(a));
+        assert_eq!(app.world.resource::<Axis<WandAxis>>().get(trigger), None);
+    }
+
+    #[test]
+    fn registers_wands_as_gamepads() {
+        let (mut app, backend, glasses, _) = connected_app("wand-gamepad-glasses");
+        app.add_plugin(InputPlugin);
+        backend.connect_wand(&glasses, 5);
+        run_frames(&mut app);
+
+        let gamepad = app
+            .world
+            .resource::<WandGamepads>()
+            .gamepad(&glasses, "5")
+            .unwrap();
+        assert_eq!(gamepad.id, WAND_GAMEPAD_ID_START);
+        assert!(app.world.resource::<Gamepads>().contains(gamepad));
+
+        let mut report = T5_WandReport {
+            buttonsValid: true,
+            analogValid: true,
+            trigger: 1.,
+            stick: Vec2::new(0.5, 0.).into(),
+            ..Default::default()
+        };
+        report.buttons.t5 = true;
+        report.buttons.x = true;
+        backend.push_wand_report(&glasses, 5, report);
+        run_frames(&mut app);
+        let buttons = app.world.resource::<Input<GamepadButton>>();
+        for (button_type, pressed) in [
+            (GamepadButtonType::Mode, true),
+            (GamepadButtonType::West, true),
+            (GamepadButtonType::RightTrigger2, true),
+            (GamepadButtonType::South, false),
+        ] {
+            let button = GamepadButton::new(gamepad, button_type);
+            assert_eq!(buttons.pressed(button), pressed, "{button_type:?}");
+        }
+        let axes = app.world.resource::<Axis<GamepadAxis>>();
+        assert_eq!(
+            axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)),
+            Some(0.5)
+        );
+        assert_eq!(
+            axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)),
+            Some(0.)
+        );
+
+        backend.disconnect_wand(&glasses, 5);
+        run_frames(&mut app);
+        assert!(!app.world.resource::<Gamepads>().contains(gamepad));
+        assert!(app.world.resource::<WandGamepads>().wands.is_empty());
+        assert!(!app
+            .world
+            .resource::<Input<GamepadButton>>()
+            .pressed(GamepadButton::new(gamepad, GamepadButtonType::Mode)));
+    }
+}
@@ -0,0 +1,1184 @@
+use std::{
+    ffi::c_void,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    bridge::ffi::{T5_FrameInfo, T5_Quat, T5_Vec3, T5_WandHandle},
+    conversions::transform_matrix_from_bevy_to_glasses_space,
+    BackendFactory, ConnectionProgress, Gameboard, Glasses, GlassesCamera, GlassesConnectionState,
+    GlassesParam, GlassesParamValue, SystemParam, T5Error, T5GameboardType, T5Result,
+    TiltFiveBackend, TiltFiveClientEvent, TiltFiveCommands, TiltFiveService, TiltFiveSettings,
+};
+
+/// Everything the main and render apps ask of the worker
+pub(crate) enum WorkerMessage {
+    Command(TiltFiveCommands),
+    /// Send a frame to the glasses, saying when it's been handed to the SDK
+    SendFrame(Glasses, FrameInfo, Sender<()>),
+    SetDx11GraphicsContext(GraphicsDevice),
+    /// Whether anything is using the spectator pose, so it's worth polling
+    TrackSpectators(bool),
+    /// Poll straight away, saying when it's done
+    #[cfg(test)]
+    Poll(Sender<()>),
+}
+
+/// A frame for the worker to send, holding the texture handles of its eyes
+pub(crate) struct FrameInfo(pub T5_FrameInfo);
+
+// SAFETY: the texture handles are only passed on to the SDK, which is built to
+// be handed them from another thread. The render app waits for the frame to be
+// sent before letting go of the textures, so they're still alive when it is.
+unsafe impl Send for FrameInfo {}
+
+/// The graphics device the glasses render with
+pub(crate) struct GraphicsDevice(pub *mut c_void);
+
+// SAFETY: the device is only passed on to the SDK, which is built to be handed
+// it from another thread, and the render app keeps it alive for as long as it
+// sends frames.
+unsafe impl Send for GraphicsDevice {}
+
+/// What the render world needs to know about a pair of connected glasses
+#[derive(Clone, Default)]
+pub(crate) struct GlassesSnapshot {
+    pub images: Option<(Handle<Image>, Handle<Image>)>,
+    /// The left and right eye positions and the glasses rotation, in gameboard
+    /// space - `None` until the glasses are tracked
+    pub eyes: Option<(T5_Vec3, T5_Vec3, T5_Quat)>,
+}
+
+/// The latest state of every pair of connected glasses, written by the worker
+/// after each poll
+#[derive(Clone, Default)]
+pub(crate) struct PoseSnapshot(Arc<Mutex<HashMap<Glasses, GlassesSnapshot>>>);
+
+impl PoseSnapshot {
+    /// The latest snapshot - or `None` if the worker is writing it right now,
+    /// rather than waiting for it
+    pub fn try_read(&self) -> Option<HashMap<Glasses, GlassesSnapshot>> {
+        self.0.try_lock().ok().map(|snapshot| snapshot.clone())
+    }
+
+    fn write(&self, snapshot: HashMap<Glasses, GlassesSnapshot>) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = snapshot;
+        }
+    }
+}
+
+/// The app's side of the worker's channels
+pub(crate) struct WorkerHandle {
+    pub sender: Sender<WorkerMessage>,
+    pub receiver: Receiver<TiltFiveClientEvent>,
+    pub snapshot: PoseSnapshot,
+}
+
+/// Have the worker poll, and wait until it has - so tests see everything the
+/// worker would get to without guessing how long that takes
+#[cfg(test)]
+pub(crate) fn poll_now(sender: &Sender<WorkerMessage>) {
+    let (done, polled) = channel();
+    if sender.send(WorkerMessage::Poll(done)).is_ok() {
+        let _ = polled.recv();
+    }
+}
+
+/// Start the thread that owns the backend, and wait for the backend to be
+/// created on it.
+///
+/// The thread stops, releasing any glasses, once every sender to it is dropped.
+pub(crate) fn spawn(
+    factory: Arc<BackendFactory>,
+    settings: TiltFiveSettings,
+) -> T5Result<WorkerHandle> {
+    let (message_sender, message_receiver) = channel();
+    let (event_sender, event_receiver) = channel();
+    let (ready_sender, ready_receiver) = channel();
+    let snapshot = PoseSnapshot::default();
+    let worker_snapshot = snapshot.clone();
+
+    thread::Builder::new()
+        .name("tilt-five".to_string())
+        .spawn(move || {
+            // Backends don't have to be `Send`, so it's created on the thread
+            // that uses it
+            let client = match factory(&settings) {
+                Ok(client) => {
+                    let _ = ready_sender.send(Ok(()));
+                    client
+                }
+                Err(err) => {
+                    let _ = ready_sender.send(Err(err));
+                    return;
+                }
+            };
+            Worker {
+                client,
+                settings,
+                receiver: message_receiver,
+                sender: event_sender,
+                snapshot: worker_snapshot,
+                service: None,
+                glasses: Default::default(),
+                connection_states: Default::default(),
+                lost: Default::default(),
+                connecting: Default::default(),
+                track_spectators: false,
+            }
+            .run();
+        })
+        .expect("Couldn't start the Tilt Five worker thread");
+
+    ready_receiver.recv().unwrap_or(Err(T5Error::Internal))?;
+    Ok(WorkerHandle {
+        sender: message_sender,
+        receiver: event_receiver,
+        snapshot,
+    })
+}
+
+struct Worker {
+    client: Box<dyn TiltFiveBackend>,
+    settings: TiltFiveSettings,
+    receiver: Receiver<WorkerMessage>,
+    sender: Sender<TiltFiveClientEvent>,
+    snapshot: PoseSnapshot,
+    /// The service params last sent to the main app - `None` until they're
+    /// first read
+    service: Option<TiltFiveService>,
+    glasses: HashMap<Glasses, ConnectedGlasses>,
+    /// The last connection state reported for each pair of glasses
+    connection_states: HashMap<Glasses, GlassesConnectionState>,
+    /// Glasses that went away while connected - they stay in `glasses`, so the
+    /// same images are rendered to once they're back
+    lost: HashMap<Glasses, LostGlasses>,
+    /// Glasses part way through connecting - including lost glasses that are
+    /// being reconnected to
+    connecting: HashMap<Glasses, PendingConnection>,
+    /// Whether the spectator pose is polled - only while a spectator camera
+    /// needs it
+    track_spectators: bool,
+}
+
+#[derive(Default)]
+struct ConnectedGlasses {
+    snapshot: GlassesSnapshot,
+    /// The pose timestamp and IPD last sent to the main app
+    last_pose: Option<(u64, f32)>,
+    /// The timestamp of the spectator pose last sent to the main app
+    last_spectator_pose: Option<u64>,
+    /// In meters - `None` until it's been read
+    ipd: Option<f32>,
+    friendly_name: Option<String>,
+    /// The cameras the app is streaming from - streamed from again when lost
+    /// glasses come back
+    cameras: Vec<GlassesCamera>,
+    /// The kind of board the glasses last saw
+    gameboard: T5GameboardType,
+    /// The wands the main app has been told are connected
+    wands: Vec<T5_WandHandle>,
+}
+
+struct PendingConnection {
+    progress: ConnectionProgress,
+    started: Instant,
+}
+
+struct LostGlasses {
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+impl Worker {
+    fn run(mut self) {
+        let mut next_poll = Instant::now();
+        loop {
+            let timeout = next_poll.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(timeout) {
+                Ok(message) => self.handle(message),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if Instant::now() >= next_poll {
+                self.poll();
+                next_poll = Instant::now() + self.settings.poll_interval;
+            }
+        }
+
+        for (id, _) in self.glasses.drain() {
+            let _ = self.client.release_glasses(id);
+        }
+        for (id, _) in self.connecting.drain() {
+            let _ = self.client.release_glasses(id);
+        }
+    }
+
+    fn send(&self, event: TiltFiveClientEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    fn handle(&mut self, message: WorkerMessage) {
+        match message {
+            WorkerMessage::Command(command) => self.process_command(command),
+            WorkerMessage::SendFrame(glasses, FrameInfo(info), sent) => {
+                let _ = unsafe { self.client.send_frame_to_glasses(&glasses, &info) };
+                let _ = sent.send(());
+            }
+            WorkerMessage::SetDx11GraphicsContext(GraphicsDevice(device)) => unsafe {
+                if let Err(err) = self.client.set_dx11_graphics_context(device) {
+                    error!("Couldn't hand the graphics context to connected glasses: {err}");
+                }
+            },
+            WorkerMessage::TrackSpectators(track) => {
+                if track && !self.track_spectators {
+                    // New spectators need the current pose, even if it hasn't moved
+                    for glasses in self.glasses.values_mut() {
+                        glasses.last_spectator_pose = None;
+                    }
+                }
+                self.track_spectators = track;
+            }
+            #[cfg(test)]
+            WorkerMessage::Poll(done) => {
+                self.poll();
+                let _ = done.send(());
+            }
+        }
+    }
+
+    fn process_command(&mut self, command: TiltFiveCommands) {
+        match command {
+            TiltFiveCommands::RefreshGlassesList => {
+                if let Ok(new_list) = self.client.list_glasses() {
+                    self.send(TiltFiveClientEvent::GlassesFound(new_list));
+                }
+            }
+            TiltFiveCommands::ConnectToGlasses(glasses_id) => {
+                if !self.glasses.contains_key(&glasses_id) {
+                    self.start_connecting(glasses_id);
+                }
+            }
+            TiltFiveCommands::ConnectToGlassesWithDisplayName(glasses_id, display_name) => {
+                self.set_display_name(&glasses_id, &display_name);
+                if !self.glasses.contains_key(&glasses_id) {
+                    self.start_connecting(glasses_id);
+                }
+            }
+            TiltFiveCommands::SetGlassesDisplayName(glasses_id, display_name) => {
+                self.set_display_name(&glasses_id, &display_name);
+            }
+            TiltFiveCommands::DisconnectFromGlasses(glasses_id) => {
+                self.connection_states.remove(&glasses_id);
+                self.lost.remove(&glasses_id);
+                if self.connecting.remove(&glasses_id).is_some() {
+                    let _ = self.client.release_glasses(glasses_id.clone());
+                }
+                if self.glasses.remove(&glasses_id).is_some() {
+                    let _ = self.client.release_glasses(glasses_id.clone());
+                    self.send(TiltFiveClientEvent::GlassesDisconnected(glasses_id));
+                }
+            }
+            TiltFiveCommands::SetGlassesImages(id, left, right) => {
+                if let Some(glasses) = self.glasses.get_mut(&id) {
+                    glasses.snapshot.images = Some((left, right));
+                }
+            }
+            TiltFiveCommands::SetCameraStream(id, camera, enabled) => {
+                let Some(glasses) = self.glasses.get_mut(&id) else {
+                    warn!("Can't stream from a camera on glasses {id} - they aren't connected");
+                    return;
+                };
+                glasses.cameras.retain(|c| *c != camera);
+                if enabled {
+                    glasses.cameras.push(camera);
+                }
+                // Lost glasses pick the stream up once they're back
+                if !self.lost.contains_key(&id) {
+                    self.configure_camera_stream(&id, camera, enabled);
+                }
+            }
+        }
+    }
+
+    fn configure_camera_stream(&mut self, id: &Glasses, camera: GlassesCamera, enabled: bool) {
+        if let Err(err) = self.client.configure_camera_stream(id, camera, enabled) {
+            error!("Couldn't configure the {camera:?} camera stream of glasses {id}: {err}");
+        }
+    }
+
+    fn set_display_name(&mut self, id: &Glasses, display_name: &str) {
+        if let Err(err) = self.client.set_glasses_display_name(id, display_name) {
+            error!("Couldn't set the display name of glasses {id}: {err}");
+        }
+    }
+
+    fn poll(&mut self) {
+        self.update_service();
+        self.reconnect_lost_glasses();
+        self.advance_connections();
+        self.get_connection_states();
+        self.get_changed_params();
+        self.get_glasses_poses();
+        self.get_wand_events();
+        self.get_camera_images();
+
+        self.snapshot.write(
+            self.glasses
+                .iter()
+                .map(|(id, glasses)| (id.clone(), glasses.snapshot.clone()))
+                .collect(),
+        );
+    }
+
+    /// Read the system params on the first poll, and again whenever the service
+    /// says they changed
+    fn update_service(&mut self) {
+        let changed = match (&self.service, self.client.get_changed_system_params()) {
+            (None, _) => vec![SystemParam::ServiceVersion, SystemParam::AttentionRequired],
+            (Some(_), Ok(changed)) if !changed.is_empty() => changed,
+            _ => return,
+        };
+        let mut service = self.service.clone().unwrap_or_default();
+        for param in changed {
+            match param {
+                SystemParam::ServiceVersion => {
+                    service.version = self.client.get_service_version().ok()
+                }
+                SystemParam::AttentionRequired => {
+                    service.attention_required =
+                        self.client.is_attention_required().unwrap_or_default()
+                }
+            }
+        }
+        if self.service.as_ref() != Some(&service) {
+            self.service = Some(service.clone());
+            self.send(TiltFiveClientEvent::ServiceChanged(service));
+        }
+    }
+
+    fn start_connecting(&mut self, id: Glasses) {
+        if self.connecting.contains_key(&id) {
+            return;
+        }
+        let progress = ConnectionProgress::Reserving;
+        self.connecting.insert(
+            id.clone(),
+            PendingConnection {
+                progress: progress.clone(),
+                started: Instant::now(),
+            },
+        );
+        self.send(TiltFiveClientEvent::GlassesConnectionProgress(id, progress));
+    }
+
+    /// Take a step on each connection in progress, so no single poll waits on
+    /// the service for long
+    fn advance_connections(&mut self) {
+        let pending: Vec<Glasses> = self.connecting.keys().cloned().collect();
+        for id in pending {
+            let Some(started) = self.connecting.get(&id).map(|pending| pending.started) else {
+                continue;
+            };
+            let result = match self.client.connect_glasses(&id) {
+                Ok(progress)
+                    if progress != ConnectionProgress::Ready
+                        && started.elapsed() > self.settings.connection_timeout =>
+                {
+                    Err(T5Error::Timeout)
+                }
+                result => result,
+            };
+
+            match result {
+                Ok(ConnectionProgress::Ready) => {
+                    self.connecting.remove(&id);
+                    self.send(TiltFiveClientEvent::GlassesConnectionProgress(
+                        id.clone(),
+                        ConnectionProgress::Ready,
+                    ));
+                    let friendly_name = self.glasses_name(&id).ok().flatten();
+                    if self.lost.remove(&id).is_some() {
+                        info!("Reconnected to glasses {id}");
+                        // The next poll reports the state they came back in
+                        self.connection_states.remove(&id);
+                        let mut cameras = vec![];
+                        if let Some(glasses) = self.glasses.get_mut(&id) {
+                            glasses.last_pose = None;
+                            cameras = glasses.cameras.clone();
+                        }
+                        for camera in cameras {
+                            self.configure_camera_stream(&id, camera, true);
+                        }
+                        self.refresh_params(&id, &[GlassesParam::Ipd, GlassesParam::FriendlyName]);
+                        self.send(TiltFiveClientEvent::GlassesRecovered(
+                            id.clone(),
+                            friendly_name,
+                        ));
+                        self.list_wands(&id);
+                    } else {
+                        self.glasses.insert(
+                            id.clone(),
+                            ConnectedGlasses {
+                                friendly_name: friendly_name.clone(),
+                                ..Default::default()
+                            },
+                        );
+                        self.send(TiltFiveClientEvent::GlassesConnected(
+                            id.clone(),
+                            friendly_name,
+                        ));
+                        self.refresh_params(&id, &[GlassesParam::Ipd]);
+                        self.list_wands(&id);
+                    }
+                }
+                Ok(progress) => {
+                    let Some(pending) = self.connecting.get_mut(&id) else {
+                        continue;
+                    };
+                    if pending.progress != progress {
+                        pending.progress = progress.clone();
+                        self.send(TiltFiveClientEvent::GlassesConnectionProgress(id, progress));
+                    }
+                }
+                Err(err) => {
+                    self.connecting.remove(&id);
+                    // Abandons the connection, if the backend hasn't already
+                    let _ = self.client.release_glasses(id.clone());
+                    self.send(TiltFiveClientEvent::GlassesConnectionProgress(
+                        id.clone(),
+                        ConnectionProgress::Failed(err.clone()),
+                    ));
+                    if self.lost.contains_key(&id) {
+                        self.reconnect_failed(id, err);
+                    } else {
+                        error!("Couldn't connect to glasses {id}: {err}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_connection_states(&mut self) {
+        let mut lost = vec![];
+        for id in self.glasses.keys() {
+            if self.lost.contains_key(id) {
+                continue;
+            }
+            let state = match self.client.get_connection_state(id) {
+                Ok(state) => state,
+                Err(err) if err.is_device_lost() => {
+                    lost.push(id.clone());
+                    continue;
+                }
+                Err(err) => {
+                    error!("Couldn't get connection state: {err}");
+                    continue;
+                }
+            };
+            if self.connection_states.insert(id.clone(), state) != Some(state) {
+                let _ = self
+                    .sender
+                    .send(TiltFiveClientEvent::GlassesConnectionStateChanged(
+                        id.clone(),
+                        state,
+                    ));
+            }
+            if state == GlassesConnectionState::Disconnected {
+                lost.push(id.clone());
+            }
+        }
+        for id in lost {
+            self.lose_glasses(&id);
+        }
+    }
+
+    /// Read the params the service says changed - and the IPD, until it's been
+    /// read once
+    fn get_changed_params(&mut self) {
+        let connected: Vec<Glasses> = self
+            .glasses
+            .keys()
+            .filter(|id| !self.lost.contains_key(*id))
+            .cloned()
+            .collect();
+        for id in connected {
+            let mut params = self
+                .client
+                .get_changed_glasses_params(&id)
+                .unwrap_or_default();
+            let missing_ipd =
+                matches!(self.glasses.get(&id), Some(glasses) if glasses.ipd.is_none());
+            if missing_ipd && !params.contains(&GlassesParam::Ipd) {
+                params.push(GlassesParam::Ipd);
+            }
+            self.refresh_params(&id, &params);
+        }
+    }
+
+    /// The glasses' friendly name, or `None` if they don't have one
+    fn glasses_name(&mut self, id: &Glasses) -> T5Result<Option<String>> {
+        self.client
+            .get_glasses_name(id)
+            .map(|name| (!name.is_empty()).then_some(name))
+    }
+
+    /// Read the params again, letting the main app know about the ones that
+    /// actually changed
+    fn refresh_params(&mut self, id: &Glasses, params: &[GlassesParam]) {
+        for param in params {
+            let value = match param {
+                GlassesParam::Ipd => match self.client.get_ipd(id) {
+                    Ok(ipd) => GlassesParamValue::Ipd(ipd * 0.001),
+                    Err(_) => continue,
+                },
+                GlassesParam::FriendlyName => match self.glasses_name(id) {
+                    Ok(name) => GlassesParamValue::FriendlyName(name),
+                    // Failing to read it keeps the last one
+                    Err(_) => continue,
+                },
+            };
+            let Some(glasses) = self.glasses.get_mut(id) else {
+                return;
+            };
+            let changed = match &value {
+                GlassesParamValue::Ipd(ipd) => glasses.ipd.replace(*ipd) != Some(*ipd),
+                GlassesParamValue::FriendlyName(name) => {
+                    std::mem::replace(&mut glasses.friendly_name, name.clone()) != *name
+                }
+            };
+            if changed {
+                self.send(TiltFiveClientEvent::GlassesParamChanged(id.clone(), value));
+            }
+        }
+    }
+
+    fn get_glasses_poses(&mut self) {
+        let mut lost = vec![];
+        let mut boards_changed = vec![];
+        for (id, glasses) in self.glasses.iter_mut() {
+            if self.lost.contains_key(id) {
+                continue;
+            }
+            let Some(ipd) = glasses.ipd else {
+                continue;
+            };
+            if self.track_spectators {
+                match self.client.get_spectator_pose(id) {
+                    Ok(pose) if glasses.last_spectator_pose != Some(pose.timestampNanos) => {
+                        glasses.last_spectator_pose = Some(pose.timestampNanos);
+                        let (transform, _) = transform_matrix_from_bevy_to_glasses_space(&pose);
+                        let _ = self.sender.send(TiltFiveClientEvent::SpectatorPoseChanged(
+                            id.clone(),
+                            transform,
+                        ));
+                    }
+                    Ok(_) | Err(T5Error::TryAgain) => {}
+                    Err(err) if err.is_device_lost() => {
+                        lost.push(id.clone());
+                        continue;
+                    }
+                    Err(err) => error!("Couldn't get spectator pose: {err}"),
+                }
+            }
+            match self.client.get_glasses_pose(id) {
+                Ok(pose) => {
+                    if let Ok(board_type) = T5GameboardType::try_from(pose.gameboardType) {
+                        if glasses.gameboard != board_type {
+                            glasses.gameboard = board_type;
+                            boards_changed.push((id.clone(), board_type));
+                        }
+                    }
+                    if glasses.last_pose == Some((pose.timestampNanos, ipd)) {
+                        continue;
+                    }
+                    glasses.last_pose = Some((pose.timestampNanos, ipd));
+
+                    let (transform, org) = transform_matrix_from_bevy_to_glasses_space(&pose);
+
+                    let lpos = org.left() * ipd + org.translation;
+                    let rpos = org.right() * ipd + org.translation;
+
+                    let _ = self.sender.send(TiltFiveClientEvent::GlassesPoseChanged(
+                        id.clone(),
+                        transform,
+                        ipd,
+                        org,
+                        Duration::from_nanos(pose.timestampNanos),
+                    ));
+
+                    let lpos = T5_Vec3 {
+                        x: lpos.x,
+                        y: lpos.y,
+                        z: lpos.z,
+                    };
+                    let rpos = T5_Vec3 {
+                        x: rpos.x,
+                        y: rpos.y,
+                        z: rpos.z,
+                    };
+
+                    glasses.snapshot.eyes = Some((lpos, rpos, pose.rotToGLS_GBD));
+                }
+                Err(err) if err.is_device_lost() => lost.push(id.clone()),
+                // The glasses can't see the board
+                Err(T5Error::TryAgain) => {}
+                Err(err) => error!("Couldn't get pose: {err}"),
+            }
+        }
+        for id in lost {
+            self.lose_glasses(&id);
+        }
+        for (id, board_type) in boards_changed {
+            match self.client.get_gameboard_size(board_type) {
+                Ok(size) => self.send(TiltFiveClientEvent::GameboardChanged(
+                    id,
+                    Gameboard::new(board_type, &size),
+                )),
+                Err(err) => error!("Couldn't get the size of a {board_type:?} board: {err}"),
+            }
+        }
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn get_wand_events(&mut self) {
+        use crate::bridge::ffi::{
+            T5_WandStreamEventType_kT5_WandStreamEventType_Connect,
+            T5_WandStreamEventType_kT5_WandStreamEventType_Desync,
+            T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect,
+            T5_WandStreamEventType_kT5_WandStreamEventType_Report,
+        };
+
+        for (id, glasses) in self.glasses.iter_mut() {
+            if self.lost.contains_key(id) {
+                continue;
+            }
+            let Ok(events) = self.client.get_wand_stream_events(id) else {
+                continue;
+            };
+            for event in events {
+                let wand = event.wandId;
+                let known = glasses.wands.contains(&wand);
+                let wand_id = wand.to_string();
+                let glasses_id = id.clone();
+                let event = match event.type_ {
+                    T5_WandStreamEventType_kT5_WandStreamEventType_Connect => {
+                        if known {
+                            continue;
+                        }
+                        glasses.wands.push(wand);
+                        TiltFiveClientEvent::WandConnected {
+                            glasses: glasses_id,
+                            wand_id,
+                        }
+                    }
+                    T5_WandStreamEventType_kT5_WandStreamEventType_Disconnect => {
+                        if !known {
+                            continue;
+                        }
+                        glasses.wands.retain(|w| *w != wand);
+                        TiltFiveClientEvent::WandDisconnected {
+                            glasses: glasses_id,
+                            wand_id,
+                        }
+                    }
+                    T5_WandStreamEventType_kT5_WandStreamEventType_Desync => {
+                        TiltFiveClientEvent::WandDesync {
+                            glasses: glasses_id,
+                            wand_id,
+                        }
+                    }
+                    T5_WandStreamEventType_kT5_WandStreamEventType_Report => {
+                        // A replay that starts after the wand connected only
+                        // has its reports
+                        if !known {
+                            glasses.wands.push(wand);
+                            let _ = self.sender.send(TiltFiveClientEvent::WandConnected {
+                                glasses: glasses_id.clone(),
+                                wand_id: wand_id.clone(),
+                            });
+                        }
+                        TiltFiveClientEvent::WantReportUpdated {
+                            glasses: glasses_id,
+                            wand_id,
+                            report: (&event.report).into(),
+                        }
+                    }
+                    _ => continue,
+                };
+                let _ = self.sender.send(event);
+            }
+        }
+    }
+
+    /// Bring the wands sent to the main app in line with the ones paired with
+    /// the glasses - the wand stream only reports changes, so this is needed
+    /// whenever glasses connect or come back
+    fn list_wands(&mut self, id: &Glasses) {
+        let wands = match self.client.list_wands(id) {
+            Ok(wands) => wands,
+            Err(err) => {
+                warn!("Couldn't list the wands for glasses {id}: {err}");
+                return;
+            }
+        };
+        let Some(glasses) = self.glasses.get_mut(id) else {
+            return;
+        };
+        for wand in glasses.wands.iter().filter(|wand| !wands.contains(wand)) {
+            let _ = self.sender.send(TiltFiveClientEvent::WandDisconnected {
+                glasses: id.clone(),
+                wand_id: wand.to_string(),
+            });
+        }
+        for wand in wands.iter().filter(|wand| !glasses.wands.contains(wand)) {
+            let _ = self.sender.send(TiltFiveClientEvent::WandConnected {
+                glasses: id.clone(),
+                wand_id: wand.to_string(),
+            });
+        }
+        glasses.wands = wands;
+    }
+
+    fn get_camera_images(&mut self) {
+        for id in self.glasses.keys() {
+            if self.lost.contains_key(id) {
+                continue;
+            }
+            match self.client.get_camera_images(id) {
+                Ok(images) => {
+                    for image in images {
+                        self.send(TiltFiveClientEvent::CameraImage(id.clone(), image));
+                    }
+                }
+                // Noticed by the connection state on the next poll
+                Err(err) if err.is_device_lost() => {}
+                Err(err) => error!("Couldn't get camera images: {err}"),
+            }
+        }
+    }
+
+    /// Release the handle of glasses that went away, and either queue them up
+    /// to be reconnected or disconnect them - depending on the reconnect policy
+    fn lose_glasses(&mut self, id: &Glasses) {
+        let Some(glasses) = self.glasses.get_mut(id) else {
+            return;
+        };
+        // Without a pose nothing is sent to the glasses until they're back
+        glasses.snapshot.eyes = None;
+        let _ = self.client.release_glasses(id.clone());
+
+        let Some(policy) = &self.settings.reconnect_policy else {
+            warn!("Lost glasses {id}");
+            self.glasses.remove(id);
+            self.connection_states.remove(id);
+            self.send(TiltFiveClientEvent::GlassesDisconnected(id.clone()));
+            return;
+        };
+
+        warn!("Lost glasses {id}, trying to reconnect");
+        self.lost.insert(
+            id.clone(),
+            LostGlasses {
+                attempts: 0,
+                next_attempt: Instant::now() + policy.interval,
+            },
+        );
+        let disconnected = GlassesConnectionState::Disconnected;
+        if self.connection_states.insert(id.clone(), disconnected) != Some(disconnected) {
+            self.send(TiltFiveClientEvent::GlassesConnectionStateChanged(
+                id.clone(),
+                disconnected,
+            ));
+        }
+        self.send(TiltFiveClientEvent::GlassesLost(id.clone()));
+    }
+
+    fn reconnect_lost_glasses(&mut self) {
+        let now = Instant::now();
+        let due: Vec<Glasses> = self
+            .lost
+            .iter()
+            .filter(|(id, lost)| lost.next_attempt <= now && !self.connecting.contains_key(*id))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in due {
+            self.start_connecting(id);
+        }
+    }
+
+    /// Try reconnecting again later, or give up on the glasses if the reconnect
+    /// policy has run out of attempts
+    fn reconnect_failed(&mut self, id: Glasses, err: T5Error) {
+        let (Some(policy), Some(lost)) = (&self.settings.reconnect_policy, self.lost.get_mut(&id))
+        else {
+            return;
+        };
+        lost.attempts += 1;
+        lost.next_attempt = Instant::now() + policy.interval;
+        if matches!(policy.max_attempts, Some(max) if lost.attempts >= max) {
+            warn!("Giving up on reconnecting to glasses {id}: {err}");
+            self.lost.remove(&id);
+            self.glasses.remove(&id);
+            self.connection_states.remove(&id);
+            self.send(TiltFiveClientEvent::GlassesDisconnected(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::MaybeUninit;
+
+    use bevy::prelude::*;
+
+    use super::*;
+    use crate::{
+        backend::{
+            client_events, connect, connected_app, connected_entity, run_frames, test_app,
+            test_app_with_settings,
+        },
+        AvailableGlasses, FakeBackend, GlassesInfo, ReconnectPolicy, TiltFiveIPD,
+    };
+
+    fn spawn_fake(backend: &FakeBackend) -> WorkerHandle {
+        let backend = backend.clone();
+        spawn(
+            Arc::new(move |_| Ok(Box::new(backend.clone()) as Box<dyn TiltFiveBackend>)),
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn polls_and_sends_frames_off_the_calling_thread() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "worker-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        backend.set_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        let worker = spawn_fake(&backend);
+
+        let _ = worker
+            .sender
+            .send(WorkerMessage::Command(TiltFiveCommands::ConnectToGlasses(
+                glasses.clone(),
+            )));
+        // Connecting takes a poll for each step
+        for _ in 0..5 {
+            poll_now(&worker.sender);
+        }
+        assert!(matches!(
+            worker
+                .snapshot
+                .try_read()
+                .and_then(|snapshot| snapshot.get(&glasses).cloned()),
+            Some(GlassesSnapshot { eyes: Some(_), .. })
+        ));
+
+        let info = unsafe { MaybeUninit::<T5_FrameInfo>::zeroed().assume_init() };
+        let (sent, frame_sent) = channel();
+        let _ = worker.sender.send(WorkerMessage::SendFrame(
+            glasses.clone(),
+            FrameInfo(info),
+            sent,
+        ));
+        frame_sent.recv().unwrap();
+        assert_eq!(backend.frames_sent(&glasses), 1);
+
+        // Dropping the app's side of the channels stops the worker, which
+        // hangs up once it's released the glasses
+        let WorkerHandle {
+            sender, receiver, ..
+        } = worker;
+        drop(sender);
+        while receiver.recv().is_ok() {}
+        assert!(!backend.is_connected(&glasses));
+    }
+
+    #[test]
+    fn reading_a_busy_snapshot_doesnt_wait() {
+        let snapshot = PoseSnapshot::default();
+        let guard = snapshot.0.lock().unwrap();
+        assert!(snapshot.try_read().is_none());
+        drop(guard);
+        assert!(snapshot.try_read().is_some());
+    }
+
+    #[test]
+    fn reports_unplugged_glasses() {
+        let (mut app, backend, glasses, entity) = connected_app("unplugged-glasses");
+        assert_eq!(
+            app.world.get::<GlassesConnectionState>(entity),
+            Some(&GlassesConnectionState::ExclusiveConnection)
+        );
+
+        backend.set_connection_state(&glasses, GlassesConnectionState::Disconnected);
+        let events = client_events(&mut app);
+        assert_eq!(
+            app.world.get::<GlassesConnectionState>(entity),
+            Some(&GlassesConnectionState::Disconnected)
+        );
+        let changes = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    TiltFiveClientEvent::GlassesConnectionStateChanged(
+                        id,
+                        GlassesConnectionState::Disconnected
+                    ) if *id == glasses
+                )
+            })
+            .count();
+        assert_eq!(changes, 1);
+    }
+
+    #[test]
+    fn reconnects_to_lost_glasses() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "lost-glasses".into();
+        backend.add_glasses(glasses.clone(), Some("Lost"));
+        let mut app = test_app_with_settings(
+            backend.clone(),
+            TiltFiveSettings {
+                reconnect_policy: Some(ReconnectPolicy {
+                    interval: Duration::ZERO,
+                    max_attempts: None,
+                }),
+                ..Default::default()
+            },
+        );
+        let entity = connect(&mut app, &glasses);
+
+        backend.set_error(&glasses, Some(T5Error::DeviceLost));
+        let mut events = client_events(&mut app);
+        // The stale handle is released, but the entity stays around
+        assert!(!backend.is_connected(&glasses));
+        assert_eq!(connected_entity(&app, &glasses), Some(entity));
+
+        backend.set_error(&glasses, None);
+        events.extend(client_events(&mut app));
+        events.extend(client_events(&mut app));
+        assert!(backend.is_connected(&glasses));
+        assert_eq!(connected_entity(&app, &glasses), Some(entity));
+        assert_eq!(
+            app.world.get::<GlassesConnectionState>(entity),
+            Some(&GlassesConnectionState::ExclusiveConnection)
+        );
+
+        let events: Vec<_> = events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    TiltFiveClientEvent::GlassesLost(_) | TiltFiveClientEvent::GlassesRecovered(..)
+                )
+            })
+            .collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                TiltFiveClientEvent::GlassesLost(lost),
+                TiltFiveClientEvent::GlassesRecovered(recovered, Some(name)),
+            ] if *lost == glasses && *recovered == glasses && name == "Lost"
+        ));
+    }
+
+    #[test]
+    fn gives_up_on_lost_glasses() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "gone-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        let mut app = test_app_with_settings(
+            backend.clone(),
+            TiltFiveSettings {
+                reconnect_policy: Some(ReconnectPolicy {
+                    interval: Duration::ZERO,
+                    max_attempts: Some(1),
+                }),
+                ..Default::default()
+            },
+        );
+        let entity = connect(&mut app, &glasses);
+
+        backend.set_connection_state(&glasses, GlassesConnectionState::Disconnected);
+        run_frames(&mut app);
+        run_frames(&mut app);
+        assert!(connected_entity(&app, &glasses).is_none());
+        assert!(app.world.get_entity(entity).is_none());
+    }
+
+    #[test]
+    fn reports_connection_progress() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "slow-glasses".into();
+        let failing: Glasses = "failing-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        backend.add_glasses(failing.clone(), None);
+        backend.with_glasses(&glasses, |g| g.ready_after = 2);
+        backend.set_error(&failing, Some(T5Error::Unavailable));
+        let mut app = test_app(backend.clone());
+
+        app.world.send_event(TiltFiveCommands::RefreshGlassesList);
+        run_frames(&mut app);
+        app.world
+            .send_event(TiltFiveCommands::ConnectToGlasses(glasses.clone()));
+        app.world
+            .send_event(TiltFiveCommands::ConnectToGlasses(failing.clone()));
+        let mut events = client_events(&mut app);
+        events.extend(client_events(&mut app));
+        let progress_of = |id: &Glasses| {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    TiltFiveClientEvent::GlassesConnectionProgress(glasses, progress)
+                        if glasses == id =>
+                    {
+                        Some(progress.clone())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            progress_of(&glasses),
+            vec![
+                ConnectionProgress::Reserving,
+                ConnectionProgress::WaitingForReady,
+                ConnectionProgress::Ready,
+            ]
+        );
+        assert_eq!(
+            progress_of(&failing),
+            vec![
+                ConnectionProgress::Reserving,
+                ConnectionProgress::Failed(T5Error::Unavailable),
+            ]
+        );
+        assert!(connected_entity(&app, &glasses).is_some());
+        assert!(connected_entity(&app, &failing).is_none());
+    }
+
+    #[test]
+    fn tracks_the_service_params() {
+        let backend = FakeBackend::new();
+        backend.set_service_version("1.3.0");
+        let mut app = test_app(backend.clone());
+
+        run_frames(&mut app);
+        assert_eq!(
+            *app.world.resource::<TiltFiveService>(),
+            TiltFiveService {
+                version: Some("1.3.0".to_string()),
+                attention_required: false,
+            }
+        );
+
+        backend.set_attention_required(true);
+        let events = client_events(&mut app);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TiltFiveClientEvent::ServiceChanged(TiltFiveService {
+                attention_required: true,
+                ..
+            })
+        )));
+        assert!(app.world.resource::<TiltFiveService>().attention_required);
+    }
+
+    #[test]
+    fn only_reports_params_that_change() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "renamed-glasses".into();
+        backend.add_glasses(glasses.clone(), Some("Before"));
+        let mut app = test_app(backend.clone());
+        let entity = connect(&mut app, &glasses);
+        assert!((app.world.get::<TiltFiveIPD>(entity).unwrap().0 - 0.059).abs() < 1e-6);
+        // The eye cameras are placed half the IPD either side of the glasses
+        let eyes = app.world.get::<Children>(entity).unwrap().to_vec();
+        let mut cameras = app.world.query_filtered::<&Transform, With<Camera3d>>();
+        let mut offsets: Vec<_> = eyes
+            .iter()
+            .filter_map(|eye| cameras.get(&app.world, *eye).ok())
+            .map(|transform| transform.translation.x)
+            .collect();
+        offsets.sort_by(f32::total_cmp);
+        assert_eq!(offsets.len(), 2);
+        assert!((offsets[0] + 0.0295).abs() < 1e-6, "{offsets:?}");
+        assert!((offsets[1] - 0.0295).abs() < 1e-6, "{offsets:?}");
+
+        backend.set_pose(&glasses, Vec3::new(0., 0., 1.), Quat::IDENTITY);
+        let events = client_events(&mut app);
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, TiltFiveClientEvent::GlassesParamChanged(..))));
+
+        backend.set_friendly_name(&glasses, Some("After"));
+        backend.set_ipd(&glasses, 59.);
+        let events = client_events(&mut app);
+        let changed: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                TiltFiveClientEvent::GlassesParamChanged(_, value) => Some(value.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![GlassesParamValue::FriendlyName(Some("After".to_string()))]
+        );
+        assert!(matches!(
+            app.world.resource::<AvailableGlasses>().glasses.get(&glasses),
+            Some(GlassesInfo::Connected { friendly_name: Some(name), .. }) if name == "After"
+        ));
+
+        // Glasses without a name report an empty one
+        backend.set_friendly_name(&glasses, None);
+        let events = client_events(&mut app);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TiltFiveClientEvent::GlassesParamChanged(_, GlassesParamValue::FriendlyName(None))
+        )));
+    }
+
+    #[test]
+    fn sets_glasses_display_names() {
+        let backend = FakeBackend::new();
+        let glasses: Glasses = "named-glasses".into();
+        backend.add_glasses(glasses.clone(), None);
+        let mut app = test_app(backend.clone());
+
+        app.world
+            .send_event(TiltFiveCommands::ConnectToGlassesWithDisplayName(
+                glasses.clone(),
+                "Player 2 - Red Team".to_string(),
+            ));
+        run_frames(&mut app);
+        assert!(backend.is_connected(&glasses));
+        assert_eq!(
+            backend.with_glasses(&glasses, |g| g.display_name.clone()),
+            Some(Some("Player 2 - Red Team".to_string()))
+        );
+
+        app.world
+            .send_event(TiltFiveCommands::SetGlassesDisplayName(
+                glasses.clone(),
+                "Player 2 - Blue Team".to_string(),
+            ));
+        run_frames(&mut app);
+        assert_eq!(
+            backend.with_glasses(&glasses, |g| g.display_name.clone()),
+            Some(Some("Player 2 - Blue Team".to_string()))
+        );
+    }
+}